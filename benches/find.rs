@@ -1,6 +1,8 @@
 use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
 use rand::{Rng as _, SeedableRng, rngs::StdRng};
-use storage_simulation::{BinOverlay, Class, Classified, TrieOverlay};
+use storage_simulation::{
+    BinOverlay, Class, Classified, CompactTrieOverlay, SortedOverlay, TrieOverlay, UnifiedOverlay,
+};
 
 pub fn criterion_benchmark(c: &mut Criterion) {
     let mut rng = StdRng::seed_from_u64(42);
@@ -25,17 +27,51 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         group.bench_function("VanillaTrieCompressed", |b| {
             b.iter(|| network.find(rng.random(), find_size))
         });
+        let node_ids = (0..num_node).map(|_| rng.random()).collect::<Vec<_>>();
+        let network = CompactTrieOverlay::from_node_ids(node_ids.clone());
+        group.bench_function("VanillaCompactTrie", |b| {
+            b.iter(|| network.find(rng.random(), find_size))
+        });
+        let network = SortedOverlay::from_node_ids(node_ids);
+        group.bench_function("VanillaSorted", |b| {
+            b.iter(|| network.find(rng.random(), find_size))
+        });
+        let node_ids = (0..num_node)
+            .map(|_| {
+                (
+                    rng.random(),
+                    8 - (rng.random_range(1.0f32..256.).log2().floor() as Class + 1),
+                )
+            })
+            .collect::<Vec<_>>();
         let mut network = Classified::new();
-        for _ in 0..num_node {
-            network.insert_node(
-                rng.random(),
-                8 - (rng.random_range(1.0f32..256.).log2().floor() as Class + 1),
-            )
+        for &(node_id, class) in &node_ids {
+            network.insert_node(node_id, class)
         }
         network.optimize();
         group.bench_function("Classified@8", |b| {
             b.iter(|| network.find(rng.random(), find_size))
         });
+        let network = UnifiedOverlay::from_classified_node_ids(node_ids);
+        group.bench_function("Unified@8", |b| {
+            b.iter(|| network.find(rng.random(), find_size))
+        });
+        // spread over every class, so that most class roots are pruned by the closest nodes
+        let node_ids = (0..num_node)
+            .map(|_| (rng.random(), rng.random_range(0..64)))
+            .collect::<Vec<_>>();
+        let mut network = Classified::new();
+        for &(node_id, class) in &node_ids {
+            network.insert_node(node_id, class)
+        }
+        network.optimize();
+        group.bench_function("Classified@64", |b| {
+            b.iter(|| network.find(rng.random(), find_size))
+        });
+        let network = UnifiedOverlay::from_classified_node_ids(node_ids);
+        group.bench_function("Unified@64", |b| {
+            b.iter(|| network.find(rng.random(), find_size))
+        });
     }
 
     let mut group = c.benchmark_group(format!("Find{find_size}@Small"));
@@ -50,6 +86,8 @@ pub fn criterion_benchmark(c: &mut Criterion) {
             network_naive.push(node_id)
         }
         network_trie.compress();
+        let network_compact = CompactTrieOverlay::from_node_ids(network_naive.clone());
+        let network_sorted = SortedOverlay::from_node_ids(network_naive.clone());
         if num_node >= 64 {
             group.bench_function(BenchmarkId::new("VanillaBin", num_node), |b| {
                 b.iter(|| network.find(rng.random(), find_size))
@@ -58,6 +96,12 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         group.bench_function(BenchmarkId::new("VanillaTrie", num_node), |b| {
             b.iter(|| network_trie.find(rng.random(), find_size))
         });
+        group.bench_function(BenchmarkId::new("VanillaCompactTrie", num_node), |b| {
+            b.iter(|| network_compact.find(rng.random(), find_size))
+        });
+        group.bench_function(BenchmarkId::new("VanillaSorted", num_node), |b| {
+            b.iter(|| network_sorted.find(rng.random(), find_size))
+        });
         if num_node <= 128 {
             group.bench_function(BenchmarkId::new("VanillaNaive", num_node), |b| {
                 b.iter(|| storage_simulation::find(&mut network_naive, rng.random(), find_size))
@@ -0,0 +1,30 @@
+use std::{path::Path, process::Command};
+
+// the commit the crate is built from, for the manifest of every run, whatever directory the run is
+// started from. `unknown` outside a git checkout, e.g. from a source archive
+fn main() {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|output| output.trim().to_string())
+    };
+    let commit = git(&["describe", "--always", "--dirty"]).unwrap_or_else(|| "unknown".into());
+    println!("cargo:rustc-env=GIT_COMMIT={commit}");
+    // the sources tell whether the tree is dirty, and the git dir which commit it is at. a path
+    // that does not exist would rerun this on every build
+    let mut paths = ["src", "benches", "Cargo.toml", "build.rs"]
+        .map(String::from)
+        .to_vec();
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        for file in ["HEAD", "index", "refs", "packed-refs"] {
+            paths.push(format!("{git_dir}/{file}"))
+        }
+    }
+    for path in paths.into_iter().filter(|path| Path::new(path).exists()) {
+        println!("cargo:rerun-if-changed={path}")
+    }
+}
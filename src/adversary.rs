@@ -0,0 +1,56 @@
+use rand::Rng;
+use rustc_hash::FxHashSet;
+
+use crate::{Class, NodeId, Overlay, Target};
+
+// the class an adversarial node announces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Claim {
+    // the class of the capacity the node actually has
+    Honest,
+    // the given class whatever the node has, possible because nobody verifies capacity
+    Inflate(Class),
+}
+
+// how adversarial nodes choose their ids
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Position {
+    Random,
+    // nodes are assigned round robin to the targets and keep the closest of `grind` random ids to
+    // their target. a single target is an eclipse attempt
+    Targeted { targets: Vec<Target>, grind: u32 },
+}
+
+impl Position {
+    // id of the `index`th adversarial node, which is ranked by the overlay as `class`, i.e. 0 for
+    // vanilla overlay
+    pub fn node_id(&self, index: usize, class: Class, rng: &mut impl Rng) -> NodeId {
+        match self {
+            Self::Random => rng.random(),
+            Self::Targeted { targets, grind } => {
+                let target = targets[index % targets.len()];
+                // the minimum of `grind` uniform distances is sampled directly instead of drawing
+                // every id, so that grinding costs the same whatever its budget
+                let mask = !0 >> class;
+                let u = rng.random::<f64>();
+                let distance = -(u.ln() / (*grind).max(1) as f64).exp_m1() * (mask as f64 + 1.);
+                let distance = (distance as NodeId).min(mask);
+                ((target ^ distance) & mask) | (rng.random::<NodeId>() & !mask)
+            }
+        }
+    }
+}
+
+// number of nodes in the `find` set of `target` that belong to the adversary
+pub fn num_captured(
+    overlay: &Overlay,
+    adversary: &FxHashSet<NodeId>,
+    target: Target,
+    find_size: usize,
+) -> usize {
+    overlay
+        .find(target, find_size)
+        .into_iter()
+        .filter(|node_id| adversary.contains(node_id))
+        .count()
+}
@@ -0,0 +1,219 @@
+use std::{fs::create_dir_all, time::UNIX_EPOCH};
+
+use rand::{Rng, SeedableRng, rng, rngs::StdRng};
+use rand_distr::Distribution;
+use rustc_hash::{FxHashMap, FxHashSet};
+use storage_simulation::{
+    Class, Classified, NodeId, Overlay, Target,
+    classified::capacity_class,
+    output::{Format, Manifest},
+    resource::capacity_distr,
+};
+
+fn main() -> anyhow::Result<()> {
+    let seed = match std::env::var("AUDIT_SEED") {
+        Ok(seed) => seed.parse()?,
+        Err(_) => rng().random(),
+    };
+    let format = match std::env::var("AUDIT_FORMAT") {
+        Ok(format) => format.parse()?,
+        Err(_) => Format::Csv,
+    };
+    create_dir_all("data/audit")?;
+    for audit_rate in [0., 0.01, 0.05, 0.2] {
+        for num_challenge in [1, 4] {
+            run(
+                audit_rate,
+                num_challenge,
+                10_000,
+                0.1,
+                100_000,
+                3,
+                8,
+                1.,
+                seed,
+                format,
+            )?
+        }
+    }
+    Ok(())
+}
+
+const NUM_ROUND: u32 = 100;
+// bytes exchanged by a challenge and its proof
+const CHALLENGE_SIZE: u64 = 1 << 10;
+
+struct Node {
+    class: Class,
+    capacity: u64,
+    dishonest: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run(
+    // probability that a node is audited in a round
+    audit_rate: f64,
+    // an audit challenges the node on this many chunks picked at random out of its claimed
+    // capacity, and the node fails if it misses any of them
+    num_challenge: u32,
+    num_node: usize,
+    dishonest_fraction: f64,
+    num_object: usize,
+    replication: usize,
+    num_class: u8,
+    skew: f32,
+    seed: u64,
+    format: Format,
+) -> anyhow::Result<()> {
+    eprintln!("Audit rate {audit_rate} Number of challenge {num_challenge} Seed {seed}");
+    let manifest = Manifest::new(seed)
+        .param("audit_rate", audit_rate)
+        .param("num_challenge", num_challenge)
+        .param("num_node", num_node)
+        .param("dishonest_fraction", dishonest_fraction)
+        .param("num_object", num_object)
+        .param("replication", replication)
+        .param("num_class", num_class)
+        .param("skew", skew);
+    let tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
+    let prefix = format!("data/audit/{tag}-{audit_rate}-{num_challenge}");
+    let mut round_output = format.create(format!("{prefix}-round"), &manifest)?;
+    let mut summary_output = format.create(format!("{prefix}-summary"), &manifest)?;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let capacity_distr = capacity_distr(num_class, skew)?;
+    let mut overlay = Classified::new();
+    let mut nodes = FxHashMap::default();
+    for _ in 0..num_node {
+        let node_id = rng.random();
+        let capacity = capacity_distr.sample(&mut rng) as u64;
+        let dishonest = rng.random_bool(dishonest_fraction);
+        // dishonest nodes claim the top class whatever they have
+        let class = if dishonest {
+            num_class - 1
+        } else {
+            capacity_class(capacity)
+        };
+        overlay.insert_node(node_id, class);
+        nodes.insert(
+            node_id,
+            Node {
+                class,
+                capacity,
+                dishonest,
+            },
+        );
+    }
+    overlay.optimize();
+    let mut overlay = Overlay::Classified(overlay);
+    // the share dishonest nodes would hold if they claimed what they have
+    let total_capacity = nodes.values().map(|node| node.capacity).sum::<u64>();
+    let fair_share = nodes
+        .values()
+        .filter(|node| node.dishonest)
+        .map(|node| node.capacity)
+        .sum::<u64>() as f64
+        / total_capacity as f64;
+
+    let mut holders = FxHashMap::<Target, Vec<NodeId>>::default();
+    let mut stored = FxHashMap::<NodeId, FxHashSet<Target>>::default();
+    for _ in 0..num_object {
+        let object = rng.random();
+        let node_ids = overlay.find(object, replication);
+        for &node_id in &node_ids {
+            stored.entry(node_id).or_default().insert(object);
+        }
+        holders.insert(object, node_ids);
+    }
+    let dishonest = nodes
+        .iter()
+        .filter(|(_, node)| node.dishonest)
+        .map(|(&node_id, _)| node_id)
+        .collect::<FxHashSet<_>>();
+    let dishonest_share = |stored: &FxHashMap<NodeId, FxHashSet<Target>>| {
+        stored
+            .iter()
+            .filter(|(node_id, _)| dishonest.contains(node_id))
+            .map(|(_, objects)| objects.len())
+            .sum::<usize>() as f64
+            / (num_object * replication) as f64
+    };
+    let initial_share = dishonest_share(&stored);
+
+    let (mut num_audit, mut num_demoted, mut num_transfer) = (0u64, 0u64, 0u64);
+    let mut node_ids = nodes.keys().copied().collect::<Vec<_>>();
+    node_ids.sort_unstable();
+    for round in 1..=NUM_ROUND {
+        let mut demoted = Vec::new();
+        for &node_id in &node_ids {
+            if !rng.random_bool(audit_rate) {
+                continue;
+            }
+            num_audit += 1;
+            let node = &nodes[&node_id];
+            // the lowest capacity of the claimed class
+            let claimed = 1u64 << node.class;
+            let stored_fraction = (node.capacity as f64 / claimed as f64).min(1.);
+            if !rng.random_bool(stored_fraction.powi(num_challenge as _)) {
+                demoted.push(node_id)
+            }
+        }
+        // a failing node drops one class per audit, so repeated audits walk it down to the class
+        // it can prove
+        for &node_id in &demoted {
+            let node = nodes.get_mut(&node_id).unwrap();
+            overlay.remove_node(node_id, node.class);
+            node.class -= 1;
+            overlay.insert_node(node_id, node.class);
+            num_demoted += 1
+        }
+        overlay.optimize();
+        // a node that moves to a lower class only gets farther from every target, so the find sets
+        // that change are exactly those of the objects it stores
+        for node_id in demoted {
+            let Some(objects) = stored.remove(&node_id) else {
+                continue;
+            };
+            for object in objects {
+                let node_ids = overlay.find(object, replication);
+                for &holder in &holders[&object] {
+                    if holder != node_id && !node_ids.contains(&holder) {
+                        stored.get_mut(&holder).unwrap().remove(&object);
+                    }
+                }
+                for &new_holder in &node_ids {
+                    if stored.entry(new_holder).or_default().insert(object) && new_holder != node_id
+                    {
+                        num_transfer += 1
+                    }
+                }
+                holders.insert(object, node_ids);
+            }
+        }
+        round_output.write(&[
+            ("round", round.into()),
+            ("dishonest_share", dishonest_share(&stored).into()),
+            ("num_audit", num_audit.into()),
+            ("num_demoted", num_demoted.into()),
+            ("num_transfer", num_transfer.into()),
+        ])?
+    }
+
+    for (metric, value) in [
+        ("fair_share", fair_share),
+        ("initial_share", initial_share),
+        ("final_share", dishonest_share(&stored)),
+        ("num_audit", num_audit as f64),
+        (
+            "audit_bytes",
+            (num_audit * num_challenge as u64 * CHALLENGE_SIZE) as f64,
+        ),
+        ("num_demoted", num_demoted as f64),
+        ("num_transfer", num_transfer as f64),
+    ] {
+        summary_output.write(&[("metric", metric.into()), ("value", value.into())])?
+    }
+    round_output.finish()?;
+    summary_output.finish()?;
+    Ok(())
+}
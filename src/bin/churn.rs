@@ -0,0 +1,152 @@
+use std::{fs::create_dir_all, time::UNIX_EPOCH};
+
+use rand::{Rng, SeedableRng, rng, rngs::StdRng};
+use rand_distr::{Distribution, Exp};
+use storage_simulation::{
+    BinOverlay, Classified, NodeId, Overlay,
+    classified::capacity_class,
+    output::{Format, Manifest},
+    resource::capacity_distr,
+    sim::{Config, Event, Simulation, Time},
+};
+
+fn main() -> anyhow::Result<()> {
+    let seed = match std::env::var("CHURN_SEED") {
+        Ok(seed) => seed.parse()?,
+        Err(_) => rng().random(),
+    };
+    let format = match std::env::var("CHURN_FORMAT") {
+        Ok(format) => format.parse()?,
+        Err(_) => Format::Csv,
+    };
+    create_dir_all("data/churn")?;
+    for classified in [false, true] {
+        run(classified, 10_000, 100_000, 3, 8, 1., seed, format)?
+    }
+    Ok(())
+}
+
+// time unit is second
+const DURATION: Time = 24 * 60 * 60;
+const MEAN_SESSION: f64 = 6. * 60. * 60.;
+const MEAN_DOWNTIME: f64 = 60. * 60.;
+const GET_INTERVAL: f64 = 0.1;
+const SAMPLE_INTERVAL: Time = 60;
+
+#[allow(clippy::too_many_arguments)]
+fn run(
+    classified: bool,
+    num_node: usize,
+    num_object: usize,
+    replication: usize,
+    num_class: u8,
+    skew: f32,
+    seed: u64,
+    format: Format,
+) -> anyhow::Result<()> {
+    eprintln!("Number of node {num_node} Number of object {num_object} Seed {seed}");
+    let strategy = if classified { "Classified" } else { "Vanilla" };
+    let config = Config {
+        replication,
+        detection_delay: 60,
+        repair_delay: 30,
+    };
+    let manifest = Manifest::new(seed)
+        .param("strategy", strategy)
+        .param("num_node", num_node)
+        .param("num_object", num_object)
+        .param("replication", replication)
+        .param("num_class", num_class)
+        .param("skew", skew)
+        .param("detection_delay", config.detection_delay)
+        .param("repair_delay", config.repair_delay);
+    let tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
+    let mut availability_output =
+        format.create(format!("data/churn/{tag}-availability"), &manifest)?;
+    let mut summary_output = format.create(format!("data/churn/{tag}-summary"), &manifest)?;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let capacity_distr = capacity_distr(num_class, skew)?;
+    let session_distr = Exp::new(1. / MEAN_SESSION)?;
+    let downtime_distr = Exp::new(1. / MEAN_DOWNTIME)?;
+    let get_distr = Exp::new(1. / GET_INTERVAL)?;
+
+    let overlay = if classified {
+        Overlay::Classified(Classified::new())
+    } else {
+        Overlay::Vanilla(BinOverlay::new())
+    };
+    let mut simulation = Simulation::new(overlay, [], config);
+    let new_node = |rng: &mut StdRng| -> (NodeId, u8) {
+        let capacity = capacity_distr.sample(rng) as u64;
+        (rng.random(), capacity_class(capacity))
+    };
+    // every node slot alternates between a session of some node and a downtime, after which a
+    // fresh node takes the slot
+    for _ in 0..num_node {
+        let (mut node_id, mut class) = new_node(&mut rng);
+        simulation.schedule(0, Event::Join { node_id, class });
+        let mut time = 0.;
+        loop {
+            time += session_distr.sample(&mut rng);
+            if time >= DURATION as f64 {
+                break;
+            }
+            simulation.schedule(time as _, Event::Leave { node_id });
+            time += downtime_distr.sample(&mut rng);
+            if time >= DURATION as f64 {
+                break;
+            }
+            (node_id, class) = new_node(&mut rng);
+            simulation.schedule(time as _, Event::Join { node_id, class })
+        }
+    }
+    let objects = (0..num_object).map(|_| rng.random()).collect::<Vec<_>>();
+    for &object in &objects {
+        simulation.schedule(0, Event::Put { object })
+    }
+    let mut time = 0.;
+    loop {
+        time += get_distr.sample(&mut rng);
+        if time >= DURATION as f64 {
+            break;
+        }
+        let object = objects[rng.random_range(0..objects.len())];
+        simulation.schedule(time as _, Event::Get { object })
+    }
+    for time in (0..=DURATION).step_by(SAMPLE_INTERVAL as _) {
+        simulation.schedule(time, Event::Sample)
+    }
+
+    simulation.run_until(DURATION);
+
+    let stats = &simulation.stats;
+    for &(time, num_available, num_remaining) in &stats.availability {
+        availability_output.write(&[
+            ("time", time.into()),
+            ("num_available", num_available.into()),
+            ("num_remaining", num_remaining.into()),
+        ])?
+    }
+    let mut summary = vec![
+        ("num_get", stats.num_get as f64),
+        ("num_failed_get", stats.num_failed_get as f64),
+        ("num_fallback_get", stats.num_fallback_get as f64),
+        ("num_repair", stats.num_repair as f64),
+        ("num_transfer", stats.num_transfer as f64),
+        ("num_lost", stats.num_lost as f64),
+    ];
+    for (metric, quantile) in [
+        ("repair_lag_p50", 0.5),
+        ("repair_lag_p90", 0.9),
+        ("repair_lag_p99", 0.99),
+    ] {
+        summary.push((metric, stats.repair_lag.value_at_quantile(quantile) as f64))
+    }
+    for (metric, value) in summary {
+        summary_output.write(&[("metric", metric.into()), ("value", value.into())])?
+    }
+    availability_output.finish()?;
+    summary_output.finish()?;
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use std::{
+    fs::create_dir_all,
+    hint::black_box,
+    time::{Instant, UNIX_EPOCH},
+};
+
+use rand::{Rng, SeedableRng, rng, rngs::StdRng};
+use rand_distr::Distribution;
+use rustc_hash::FxHashMap;
+use storage_simulation::{
+    Classified, UnifiedOverlay,
+    classified::capacity_class,
+    fairness::Fairness,
+    output::{Format, Manifest},
+    resource::{capacity_distr, max_capacity},
+};
+
+// how the cost of a classified find and the fairness of the load change with the number of
+// classes, i.e. with capacities spanning more orders of magnitude
+fn main() -> anyhow::Result<()> {
+    let seed = match std::env::var("CLASSES_SEED") {
+        Ok(seed) => seed.parse()?,
+        Err(_) => rng().random(),
+    };
+    let format = match std::env::var("CLASSES_FORMAT") {
+        Ok(format) => format.parse()?,
+        Err(_) => Format::Csv,
+    };
+    create_dir_all("data/classes")?;
+    let (num_node, num_find, find_size, skew) = (10_000usize, 1_000_000usize, 3, 1.);
+    let manifest = Manifest::new(seed)
+        .param("num_node", num_node)
+        .param("num_find", num_find)
+        .param("find_size", find_size)
+        .param("skew", skew);
+    let tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
+    let mut output = format.create(format!("data/classes/{tag}"), &manifest)?;
+    for num_class in [1, 2, 4, 8, 16, 24, 32, 48, 64] {
+        eprintln!("Number of class {num_class}");
+        // the same ids and targets for every number of classes
+        let mut rng = StdRng::seed_from_u64(seed);
+        let capacity_distr = capacity_distr(num_class, skew)?;
+        let nodes = (0..num_node)
+            .map(|_| {
+                // the largest capacities may round up past the range
+                let capacity =
+                    (capacity_distr.sample(&mut rng) as u64).min(max_capacity(num_class));
+                (rng.random(), capacity)
+            })
+            .collect::<Vec<_>>();
+        let mut overlay = Classified::new();
+        let mut node_ids = Vec::new();
+        for &(node_id, capacity) in &nodes {
+            let class = capacity_class(capacity);
+            overlay.try_insert_node(node_id, class)?;
+            node_ids.push((node_id, class))
+        }
+        overlay.optimize();
+        let mut num_class_nodes = vec![0; num_class as usize];
+        for &(_, class) in &node_ids {
+            num_class_nodes[class as usize] += 1
+        }
+        let unified_overlay = UnifiedOverlay::from_classified_node_ids(node_ids);
+        let targets = (0..num_find).map(|_| rng.random()).collect::<Vec<_>>();
+
+        let start = Instant::now();
+        for &target in &targets {
+            black_box(overlay.find(target, find_size));
+        }
+        let classified_find_ns = start.elapsed().as_nanos() as f64 / num_find as f64;
+        let start = Instant::now();
+        for &target in &targets {
+            black_box(unified_overlay.find(target, find_size));
+        }
+        let unified_find_ns = start.elapsed().as_nanos() as f64 / num_find as f64;
+        let mut hit_counts = FxHashMap::<_, u64>::default();
+        for &target in &targets {
+            for node_id in unified_overlay.find(target, find_size) {
+                *hit_counts.entry(node_id).or_default() += 1
+            }
+        }
+
+        let fairness = Fairness::new(
+            &nodes
+                .iter()
+                .map(|(node_id, capacity)| {
+                    (hit_counts.get(node_id).copied().unwrap_or(0), *capacity)
+                })
+                .collect::<Vec<_>>(),
+        );
+        let num_nonempty_class = num_class_nodes.iter().filter(|&&n| n > 0).count();
+        // both grow with the number of classes, by about these per class
+        let mut record = vec![
+            ("num_class", num_class.into()),
+            ("num_nonempty_class", num_nonempty_class.into()),
+            ("classified_find_ns", classified_find_ns.into()),
+            ("unified_find_ns", unified_find_ns.into()),
+            (
+                "classified_find_ns_per_class",
+                (classified_find_ns / num_nonempty_class as f64).into(),
+            ),
+            (
+                "unified_find_ns_per_class",
+                (unified_find_ns / num_nonempty_class as f64).into(),
+            ),
+        ];
+        record.extend(
+            fairness
+                .fields()
+                .into_iter()
+                .map(|(metric, value)| (metric, value.into())),
+        );
+        output.write(&record)?
+    }
+    output.finish()
+}
@@ -0,0 +1,126 @@
+use std::{fs::create_dir_all, time::UNIX_EPOCH};
+
+use rand::{Rng, SeedableRng, rng, rngs::StdRng};
+use rand_distr::{Distribution, Normal};
+use storage_simulation::{
+    Classified, NodeId, Overlay,
+    classified::{self, capacity_class},
+    keyspace,
+    output::{Format, Manifest},
+    resource::{capacity_distr, max_capacity},
+};
+
+// nodes add and lose disk over time, and move to another class when their capacity crosses a
+// power of two. a class change halves or doubles the range of a node, and the objects in the
+// difference migrate
+fn main() -> anyhow::Result<()> {
+    let seed = match std::env::var("DRIFT_SEED") {
+        Ok(seed) => seed.parse()?,
+        Err(_) => rng().random(),
+    };
+    let format = match std::env::var("DRIFT_FORMAT") {
+        Ok(format) => format.parse()?,
+        Err(_) => Format::Csv,
+    };
+    create_dir_all("data/drift")?;
+    let (num_node, num_round, find_size, num_class, skew, num_target) =
+        (10_000usize, 100usize, 3usize, 8u8, 1., 10_000usize);
+    let manifest = Manifest::new(seed)
+        .param("num_node", num_node)
+        .param("num_round", num_round)
+        .param("find_size", find_size)
+        .param("num_class", num_class)
+        .param("skew", skew)
+        .param("drift_rate", DRIFT_RATE)
+        .param("drift_sigma", DRIFT_SIGMA)
+        .param("num_target", num_target);
+    let tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
+    let mut round_output = format.create(format!("data/drift/{tag}-round"), &manifest)?;
+    let mut event_output = format.create(format!("data/drift/{tag}-event"), &manifest)?;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let capacity_distr = capacity_distr(num_class, skew)?;
+    let step_distr = Normal::new(0., DRIFT_SIGMA)?;
+    let mut overlay = Overlay::Classified(Classified::new());
+    // (node id, capacity)
+    let mut nodes = Vec::<(NodeId, u64)>::new();
+    for _ in 0..num_node {
+        let node_id = rng.random();
+        let capacity = capacity_distr.sample(&mut rng) as u64;
+        overlay.try_insert_node(node_id, capacity_class(capacity))?;
+        nodes.push((node_id, capacity))
+    }
+    overlay.optimize();
+    let node_ids = |nodes: &[(NodeId, u64)]| {
+        nodes
+            .iter()
+            .map(|&(node_id, capacity)| (node_id, capacity_class(capacity)))
+            .collect::<Vec<classified::NodeId>>()
+    };
+
+    for round in 1..=num_round {
+        let round_before = node_ids(&nodes);
+        let overlay_before = overlay.clone();
+        let total_capacity = nodes.iter().map(|&(_, capacity)| capacity).sum::<u64>();
+        let (mut num_drift, mut num_class_change, mut capacity_change) = (0usize, 0usize, 0u64);
+        let mut event_migrated = 0.;
+        for index in 0..nodes.len() {
+            if !rng.random_bool(DRIFT_RATE) {
+                continue;
+            }
+            num_drift += 1;
+            let (node_id, capacity) = nodes[index];
+            // a step of `DRIFT_SIGMA` is a factor of two
+            let factor = step_distr.sample(&mut rng).exp2();
+            let new_capacity =
+                ((capacity as f64 * factor).round() as u64).clamp(1, max_capacity(num_class));
+            capacity_change += capacity.abs_diff(new_capacity);
+            let (class, new_class) = (capacity_class(capacity), capacity_class(new_capacity));
+            if class == new_class {
+                nodes[index].1 = new_capacity;
+                continue;
+            }
+            num_class_change += 1;
+            let before = node_ids(&nodes);
+            nodes[index].1 = new_capacity;
+            let change = keyspace::change(&before, &node_ids(&nodes), find_size);
+            assert!(overlay.update_class(node_id, new_class));
+            event_migrated += change.moved;
+            event_output.write(&[
+                ("round", round.into()),
+                ("node_id", node_id.into()),
+                ("class", class.into()),
+                ("new_class", new_class.into()),
+                // the node alone gains or loses the difference, so this is also its change of
+                // ownership over `find_size`
+                ("migrated", change.moved.into()),
+            ])?
+        }
+        overlay.optimize();
+        // changes in the same round may cancel out on some targets
+        let change = keyspace::change(&round_before, &node_ids(&nodes), find_size);
+        let sampled =
+            keyspace::sampled_change(&overlay_before, &overlay, find_size, num_target, &mut rng);
+        round_output.write(&[
+            ("round", round.into()),
+            ("num_drift", num_drift.into()),
+            ("num_class_change", num_class_change.into()),
+            // what moves for load exactly proportional to capacity
+            (
+                "capacity_change",
+                (capacity_change as f64 / total_capacity as f64).into(),
+            ),
+            ("migrated", change.moved.into()),
+            ("event_migrated", event_migrated.into()),
+            ("sampled_migrated", sampled.moved.into()),
+        ])?
+    }
+    round_output.finish()?;
+    event_output.finish()?;
+    Ok(())
+}
+
+// chance of a node's capacity to change in a round
+const DRIFT_RATE: f64 = 0.01;
+// standard deviation of the change in log2 of the capacity
+const DRIFT_SIGMA: f64 = 0.5;
@@ -0,0 +1,136 @@
+use std::{fs::create_dir_all, time::UNIX_EPOCH};
+
+use rand::{Rng, SeedableRng, rng, rngs::StdRng};
+use rand_distr::Distribution;
+use storage_simulation::{
+    BinOverlay, Classified, Overlay,
+    classified::capacity_class,
+    output::{Format, Manifest},
+    placement::{Overflow, Placement},
+    resource::capacity_distr,
+};
+
+fn main() -> anyhow::Result<()> {
+    let seed = match std::env::var("FILL_SEED") {
+        Ok(seed) => seed.parse()?,
+        Err(_) => rng().random(),
+    };
+    let format = match std::env::var("FILL_FORMAT") {
+        Ok(format) => format.parse()?,
+        Err(_) => Format::Csv,
+    };
+    create_dir_all("data/fill")?;
+    for classified in [false, true] {
+        for overflow in [
+            Overflow::NextClosest { max_candidate: 3 },
+            Overflow::NextClosest { max_candidate: 32 },
+            Overflow::Spill { max_target: 8 },
+        ] {
+            run(classified, overflow, 10_000, 3, 4, 8, 1., seed, format)?
+        }
+    }
+    Ok(())
+}
+
+// number of fill level samples over a run
+const NUM_SAMPLE: u64 = 100;
+
+#[allow(clippy::too_many_arguments)]
+fn run(
+    classified: bool,
+    overflow: Overflow,
+    num_node: usize,
+    replication: usize,
+    // replicas a node stores per unit of capacity
+    slot_per_capacity: u64,
+    num_class: u8,
+    skew: f32,
+    seed: u64,
+    format: Format,
+) -> anyhow::Result<()> {
+    eprintln!("Number of node {num_node} Overflow {overflow:?} Seed {seed}");
+    let strategy = if classified { "Classified" } else { "Vanilla" };
+    let (overflow_name, overflow_limit) = match overflow {
+        Overflow::NextClosest { max_candidate } => ("next_closest", max_candidate),
+        Overflow::Spill { max_target } => ("spill", max_target),
+    };
+    let manifest = Manifest::new(seed)
+        .param("strategy", strategy)
+        .param("overflow", overflow_name)
+        .param("overflow_limit", overflow_limit)
+        .param("num_node", num_node)
+        .param("replication", replication)
+        .param("slot_per_capacity", slot_per_capacity)
+        .param("num_class", num_class)
+        .param("skew", skew);
+    let tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
+    let prefix = format!(
+        "data/fill/{tag}-{}-{overflow_name}-{overflow_limit}",
+        strategy.to_lowercase()
+    );
+    let mut fill_output = format.create(format!("{prefix}-fill"), &manifest)?;
+    let mut summary_output = format.create(format!("{prefix}-summary"), &manifest)?;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let capacity_distr = capacity_distr(num_class, skew)?;
+    let mut overlay = if classified {
+        Overlay::Classified(Classified::new())
+    } else {
+        Overlay::Vanilla(BinOverlay::new())
+    };
+    let mut nodes = Vec::new();
+    for _ in 0..num_node {
+        let node_id = rng.random();
+        let capacity = capacity_distr.sample(&mut rng) as u64;
+        let class = capacity_class(capacity);
+        overlay.insert_node(node_id, class);
+        nodes.push((node_id, capacity * slot_per_capacity))
+    }
+    overlay.optimize();
+    let total_capacity = nodes.iter().map(|&(_, capacity)| capacity).sum::<u64>();
+    let mut placement = Placement::new(overlay, nodes, overflow);
+
+    // as many objects as would fill every node if placement were perfect
+    let num_object = total_capacity / replication as u64;
+    // every object is a sample when there are fewer objects than samples
+    let sample_interval = (num_object / NUM_SAMPLE).max(1);
+    for index in 1..=num_object {
+        placement.place(rng.random(), replication);
+        if index % sample_interval == 0 || index == num_object {
+            let stats = &placement.stats;
+            fill_output.write(&[
+                ("num_object", index.into()),
+                ("fill", placement.fill().into()),
+                ("num_full_node", placement.num_full().into()),
+                ("num_failed", stats.num_failed.into()),
+                ("num_displaced", stats.num_displaced.into()),
+            ])?
+        }
+    }
+
+    let stats = &placement.stats;
+    // fill level at the first failure is the capacity the scheme can use without degrading
+    let first_failure_fill = stats
+        .first_failure
+        .map(|index| (index - 1) as f64 * replication as f64 / total_capacity as f64);
+    for (metric, value) in [
+        ("num_object", num_object as f64),
+        ("num_placed", stats.num_placed as f64),
+        ("num_failed", stats.num_failed as f64),
+        ("num_displaced", stats.num_displaced as f64),
+        ("final_fill", placement.fill()),
+        (
+            "first_failure",
+            stats
+                .first_failure
+                .map(|index| index as f64)
+                .unwrap_or(f64::NAN),
+        ),
+        ("first_failure_fill", first_failure_fill.unwrap_or(f64::NAN)),
+    ] {
+        summary_output.write(&[("metric", metric.into()), ("value", value.into())])?
+    }
+    fill_output.finish()?;
+    summary_output.finish()?;
+    Ok(())
+}
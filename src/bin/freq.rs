@@ -1,40 +1,153 @@
 use std::{
     collections::HashMap,
-    fs::{File, create_dir_all},
-    io::Write,
+    fs::{File, create_dir_all, rename},
+    io::{BufReader, BufWriter, Read, Write},
     iter::repeat_with,
+    path::{Path, PathBuf},
     time::UNIX_EPOCH,
 };
 
-use hdrhistogram::Histogram;
+use anyhow::{bail, ensure};
+use hdrhistogram::{
+    Histogram,
+    serialization::{Deserializer, Serializer, V2Serializer},
+};
 use rand::{Rng, SeedableRng, rng, rngs::StdRng};
-use rand_distr::{Distribution, Zipf};
+use rand_distr::Distribution;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use storage_simulation::{Classified, Overlay, BinOverlay};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use storage_simulation::{
+    BinOverlay, Classified, NodeId, Overlay,
+    fairness::Fairness,
+    keyspace, model,
+    network::{Coordinate, Link, Location, NetworkModel},
+    output::{Format, Manifest},
+    progress::Progress,
+    read::ReadPolicy,
+    resource::{ClassPolicy, Resources, capacity_distr},
+    snapshot::{self, Snapshot},
+    stats::Summary,
+};
 
 fn main() -> anyhow::Result<()> {
     let num_find: u32 = 1_000_000;
     let num_node = 10_000;
     let find_size: usize = 3;
 
-    let mut rng = rng();
+    // every run is seeded with the same seed, so the strategies are compared on the same
+    // populations and targets (as long as the populations are generated in the same way)
+    let seed = match std::env::var("FREQ_SEED") {
+        Ok(seed) => seed.parse()?,
+        Err(_) => rng().random(),
+    };
+    let format = match std::env::var("FREQ_FORMAT") {
+        Ok(format) => format.parse()?,
+        Err(_) => Format::Csv,
+    };
+    let class_policy = std::env::var("FREQ_CLASS_POLICY").unwrap_or_else(|_| "storage".into());
+    // comma separated, every find is read once under each of them
+    let read_policies = match std::env::var("FREQ_READ_POLICY") {
+        Ok(policies) => policies
+            .split(',')
+            .map(str::parse)
+            .collect::<anyhow::Result<Vec<_>>>()?,
+        Err(_) => vec![ReadPolicy::Primary, ReadPolicy::Random, ReadPolicy::Nearest],
+    };
+    // up to 64, one class per bit of the id
+    let num_class: u8 = match std::env::var("FREQ_NUM_CLASS") {
+        Ok(num_class) => num_class.parse()?,
+        Err(_) => 8,
+    };
+    // before any output is created
+    if !(1..=NodeId::BITS).contains(&(num_class as u32)) {
+        bail!("{num_class} classes out of 1 to {}", NodeId::BITS)
+    }
+    // `exact` or `sampled`. the exact partition of the key space takes about half a second per
+    // sample with 8 classes but minutes with 16, and grows further with more
+    let exact_ownership = match std::env::var("FREQ_OWNERSHIP").as_deref() {
+        Ok("exact") => true,
+        Ok("sampled") => false,
+        Ok(method) => bail!("unknown ownership method {method}"),
+        Err(_) => num_class <= MAX_EXACT_OWNERSHIP_CLASS,
+    };
+    let ownership = if exact_ownership { "exact" } else { "sampled" };
     create_dir_all("data/freq")?;
+    let mut series = Vec::new();
     for classified in [false, true] {
-        run(
+        series.push(run(
             100,
             classified,
             num_node,
             num_find,
             find_size,
-            8,
+            num_class,
             1.,
-            StdRng::from_rng(&mut rng),
-        )?
+            &class_policy,
+            &read_policies,
+            exact_ownership,
+            seed,
+            format,
+        )?)
     }
 
-    Ok(())
+    // the samples of the same index have the same population and targets, so the difference of
+    // the strategies is paired and has less variance than either
+    let manifest = Manifest::new(seed)
+        .param("num_node", num_node)
+        .param("num_find", num_find)
+        .param("find_size", find_size)
+        .param("num_class", num_class)
+        .param("skew", 1.)
+        .param("class_policy", &*class_policy)
+        .param(
+            "read_policy",
+            read_policies
+                .iter()
+                .map(ReadPolicy::name)
+                .collect::<Vec<_>>()
+                .join(","),
+        )
+        .param("ownership", ownership);
+    let tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
+    let mut compare_output = format.create(format!("data/freq/{tag}-compare"), &manifest)?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    for (field, vanilla_samples) in &series[0] {
+        let classified_samples = series[1]
+            .iter()
+            .find(|(other_field, _)| other_field == field)
+            .map(|(_, samples)| samples)
+            .unwrap();
+        let differences = classified_samples
+            .iter()
+            .zip(vanilla_samples)
+            .map(|(classified, vanilla)| classified - vanilla)
+            .collect::<Vec<_>>();
+        let (source, metric) = *field;
+        let mut record = vec![("source", source.into()), ("metric", metric.into())];
+        record.extend(
+            Summary::new(&differences, NUM_RESAMPLE, &mut rng)
+                .fields()
+                .into_iter()
+                .map(|(name, value)| (name, value.into())),
+        );
+        compare_output.write(&record)?
+    }
+    compare_output.finish()
 }
 
+// the default is the exact ownership up to this many classes, and sampled ownership beyond
+const MAX_EXACT_OWNERSHIP_CLASS: u8 = 8;
+
+// for the bootstrap confidence intervals
+const NUM_RESAMPLE: usize = 10_000;
+
+// per metric, as (source, metric), the value of every sample in order
+type Series = Vec<((&'static str, &'static str), Vec<f64>)>;
+
+// (source, metric, value) of a single sample
+type SampleField = (&'static str, &'static str, f64);
+
 #[allow(clippy::too_many_arguments)]
 fn run(
     num_sample: usize,
@@ -44,143 +157,607 @@ fn run(
     find_size: usize,
     num_class: u8,
     skew: f32,
-    mut rng: impl Rng,
-) -> anyhow::Result<()> {
-    eprintln!("Number of node {num_node} Number of class {num_class} Skew {skew}");
+    class_policy_name: &str,
+    read_policies: &[ReadPolicy],
+    exact_ownership: bool,
+    seed: u64,
+    format: Format,
+) -> anyhow::Result<Series> {
+    let class_policy = class_policy_name.parse::<ClassPolicy>()?;
+    eprintln!("Number of node {num_node} Number of class {num_class} Skew {skew} Seed {seed}");
 
-    let tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
-    let mut node_output = File::create(format!("data/freq/{tag}-node.csv"))?;
-    let mut capacity_output = File::create(format!("data/freq/{tag}-capacity.csv"))?;
-    let mut class_output = File::create(format!("data/freq/{tag}-class.csv"))?;
-    let header = "strategy,num_node,num_find,find_size,num_class,skew";
-    writeln!(node_output, "{header},freq,quantile")?;
-    writeln!(capacity_output, "{header},freq,quantile")?;
-    writeln!(
-        class_output,
-        "{header},class,num_class_node,class_capacity,class_hit_count"
-    )?;
-    let prefix = format!(
-        "{},{num_node},{num_find},{find_size},{num_class},{skew}",
-        if classified { "Classified" } else { "Vanilla" }
-    );
-
-    let capacity_distr = Zipf::new(((1usize << num_class) - 1) as f32, skew)?;
-    #[derive(Default, Clone)]
-    struct Class {
-        num_node: u64,
-        capacity: u64,
-        hit_count: u64,
-    }
-    let (node_counts, capacity_counts, classes) = repeat_with(|| StdRng::from_rng(&mut rng))
+    let strategy = if classified { "Classified" } else { "Vanilla" };
+    let ownership = if exact_ownership { "exact" } else { "sampled" };
+    let read_policy = read_policies
+        .iter()
+        .map(ReadPolicy::name)
+        .collect::<Vec<_>>()
+        .join(",");
+    let manifest = Manifest::new(seed)
+        .param("strategy", strategy)
+        .param("num_node", num_node)
+        .param("num_find", num_find)
+        .param("find_size", find_size)
+        .param("num_class", num_class)
+        .param("skew", skew)
+        .param("class_policy", class_policy_name)
+        .param("read_policy", &*read_policy)
+        .param("ownership", ownership);
+    let mut tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
+    // a run that resumes every sample ends within the second it starts
+    while Path::new(&format!("data/freq/{tag}-node.{}", format.extension())).exists() {
+        tag += 1
+    }
+    let mut node_output = format.create(format!("data/freq/{tag}-node"), &manifest)?;
+    let mut capacity_output = format.create(format!("data/freq/{tag}-capacity"), &manifest)?;
+    let mut resource_output = format.create(format!("data/freq/{tag}-resource"), &manifest)?;
+    let mut class_output = format.create(format!("data/freq/{tag}-class"), &manifest)?;
+    let mut rank_output = format.create(format!("data/freq/{tag}-rank"), &manifest)?;
+    let mut read_output = format.create(format!("data/freq/{tag}-read"), &manifest)?;
+    let mut fairness_output = format.create(format!("data/freq/{tag}-fairness"), &manifest)?;
+    let mut summary_output = format.create(format!("data/freq/{tag}-summary"), &manifest)?;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let capacity_distr = capacity_distr(num_class, skew)?;
+    // with this set, every sample population is saved on first run and loaded on later runs
+    let snapshot_dir = std::env::var_os("FREQ_SNAPSHOT_DIR").map(PathBuf::from);
+    if let Some(snapshot_dir) = &snapshot_dir {
+        create_dir_all(snapshot_dir)?
+    }
+    // with this set, the outcome of every sample is saved once it finishes, and a rerun with the
+    // same parameters and seed only runs the samples that have not
+    let checkpoint_dir = std::env::var_os("FREQ_CHECKPOINT_DIR").map(PathBuf::from);
+    if let Some(checkpoint_dir) = &checkpoint_dir {
+        create_dir_all(checkpoint_dir)?
+    }
+    let progress = Progress::new(strategy, "find", num_sample);
+    let progress = &progress;
+    let read_policy = &read_policy;
+    let num_hit = num_find as u64 * find_size as u64;
+    let mut outcome = repeat_with(|| StdRng::from_rng(&mut rng))
         .take(num_sample)
+        .enumerate()
         .collect::<Vec<_>>()
         .into_par_iter()
-        .map(move |mut rng| {
-            let mut network = if classified {
-                Overlay::Classified(Classified::new())
-            } else {
-                Overlay::Vanilla(BinOverlay::new())
-            };
-
-            struct Node {
-                capacity: u64,
-                hit_count: u64,
+        .map(move |(index, mut rng)| {
+            // every sample has its own rng drawn upfront, so the samples left are the same as if
+            // none had been skipped
+            let checkpoint_path = checkpoint_dir.as_ref().map(|checkpoint_dir| {
+                checkpoint_dir.join(format!(
+                    "{strategy}-{class_policy_name}-{read_policy}-{ownership}-{num_node}-{num_class}-{skew}-{num_find}-{find_size}-{seed}-{index}.bin"
+                ))
+            });
+            if let Some(path) = checkpoint_path.as_ref().filter(|path| path.exists()) {
+                let outcome = Outcome::load(path)?;
+                progress.resume();
+                return Ok((index, outcome));
             }
-            let mut nodes = HashMap::new();
-            fn node_class(capacity: u64) -> u8 {
-                (capacity as f32).log2().floor() as _
-            }
-            // let mut total_capacity = 0;
-            for _ in 0..num_node {
-                let node_id = rng.random();
-                let capacity = capacity_distr.sample(&mut rng) as _;
-                // total_capacity += capacity;
-                nodes.insert(
-                    node_id,
-                    Node {
-                        capacity,
-                        hit_count: 0,
-                    },
-                );
-                match &mut network {
-                    Overlay::Vanilla(network) => network.insert_node(node_id),
-                    Overlay::Classified(network) => {
-                        network.insert_node(node_id, node_class(capacity))
+            let populate = |rng: &mut StdRng| {
+                let mut network = if classified {
+                    Overlay::Classified(Classified::new())
+                } else {
+                    Overlay::Vanilla(BinOverlay::new())
+                };
+                let mut nodes = Vec::new();
+                for _ in 0..num_node {
+                    let node_id = rng.random();
+                    let resources = Resources {
+                        storage: capacity_distr.sample(rng) as _,
+                        bandwidth: capacity_distr.sample(rng) as _,
+                        uptime: rng.random_range(0.2..=1.),
+                    };
+                    let class = class_policy.class(&resources).min(num_class - 1);
+                    nodes.push(snapshot::Node {
+                        id: node_id,
+                        class,
+                        resources,
+                    });
+                    // a colliding id fails the run rather than silently skewing the counts
+                    network.try_insert_node(node_id, class)?
+                }
+                anyhow::Ok(Snapshot {
+                    nodes,
+                    overlay: network,
+                })
+            };
+            // the population has an rng of its own, so the rest of the sample draws the same
+            // whether it is populated or loaded
+            let mut populate_rng = StdRng::from_rng(&mut rng);
+            let snapshot = match &snapshot_dir {
+                Some(snapshot_dir) => {
+                    let path = snapshot_dir.join(format!(
+                        "{strategy}-{class_policy_name}-{num_node}-{num_class}-{skew}-{seed}-{index}.bin"
+                    ));
+                    if path.exists() {
+                        Snapshot::load(&path)?
+                    } else {
+                        let snapshot = populate(&mut populate_rng)?;
+                        snapshot.save(&path)?;
+                        snapshot
                     }
                 }
+                None => populate(&mut populate_rng)?,
+            };
+
+            // readers are the nodes themselves, scattered in a synthetic coordinate space
+            let mut network = NetworkModel::new(None);
+            for node in &snapshot.nodes {
+                let upload = node.resources.bandwidth as f64 * 1e6;
+                network.insert_node(
+                    node.id,
+                    Link {
+                        location: Location::Coordinate(Coordinate::synthetic(&mut rng)),
+                        upload,
+                        download: upload * 10.,
+                    },
+                )?
             }
+            let mut loads = snapshot
+                .nodes
+                .iter()
+                .map(|node| {
+                    let load = Load {
+                        rank_hit_counts: vec![0; find_size],
+                        read_counts: vec![0; read_policies.len()],
+                    };
+                    (node.id, load)
+                })
+                .collect::<HashMap<_, _>>();
             for _ in 0..num_find {
-                let node_ids = network.find(rng.random(), find_size);
-                for node_id in node_ids {
-                    nodes.get_mut(&node_id).unwrap().hit_count += 1
+                let node_ids = snapshot.overlay.find(rng.random(), find_size);
+                for (rank, node_id) in node_ids.iter().enumerate() {
+                    loads.get_mut(node_id).unwrap().rank_hit_counts[rank] += 1
+                }
+                let client = snapshot.nodes[rng.random_range(0..snapshot.nodes.len())].id;
+                for (index, policy) in read_policies.iter().enumerate() {
+                    if let Some(node_id) = policy.select(client, &node_ids, &network, &mut rng) {
+                        loads.get_mut(&node_id).unwrap().read_counts[index] += 1
+                    }
                 }
             }
-
-            let mut node_counts = Histogram::<u32>::new(1).unwrap();
-            let mut capacity_counts = Histogram::<u32>::new(1).unwrap();
-            let mut classes = vec![Class::default(); num_class as _];
-            for node in nodes.values() {
-                node_counts.record(node.hit_count).unwrap();
-                capacity_counts
-                    .record_n(
-                        node.hit_count * 1_000_000 / node.capacity,
-                        node.capacity as _,
-                    )
-                    .unwrap();
-                let class = &mut classes[node_class(node.capacity) as usize];
-                class.num_node += 1;
-                class.capacity += node.capacity;
-                class.hit_count += node.hit_count
+            // the share of the finds every node should get, without sampling noise if exact, or
+            // over as many other targets as there are finds if not
+            let ownership = if exact_ownership {
+                keyspace::ownership(
+                    &snapshot
+                        .nodes
+                        .iter()
+                        .map(|node| (node.id, if classified { node.class } else { 0 }))
+                        .collect::<Vec<_>>(),
+                    find_size,
+                )
+            } else {
+                keyspace::sampled_ownership(
+                    &snapshot.overlay,
+                    &snapshot.nodes.iter().map(|node| node.id).collect::<Vec<_>>(),
+                    find_size,
+                    num_find as _,
+                    &mut rng,
+                )
+            };
+            let mut outcome = Outcome::new(
+                num_class,
+                find_size,
+                read_policies.len(),
+                &snapshot.nodes,
+                &loads,
+                &ownership,
+            );
+            outcome.expect(classified, num_find, find_size);
+            if let Some(path) = &checkpoint_path {
+                outcome.save(path)?
             }
-            (node_counts, capacity_counts, classes)
+            progress.finish(num_find as _);
+            anyhow::Ok((index, outcome))
         })
-        .reduce(
-            || {
-                (
-                    Histogram::<u32>::new(1).unwrap(),
-                    Histogram::<u32>::new(1).unwrap(),
-                    vec![Class::default(); num_class as _],
-                )
-            },
-            |(a1, b1, c1), (a2, b2, c2)| {
+        .map(|sample| {
+            let (index, mut outcome) = sample?;
+            outcome.record_sample(index, num_hit);
+            anyhow::Ok(outcome)
+        })
+        .try_reduce(
+            || Outcome::empty(num_class, find_size, read_policies.len()),
+            |a, b| Ok(a.merge(b)),
+        )?;
+
+    for value in outcome.node_counts.iter_recorded() {
+        node_output.write(&[
+            (
+                "freq",
+                (value.value_iterated_to() as f64 / num_hit as f64).into(),
+            ),
+            ("quantile", value.quantile().into()),
+        ])?
+    }
+    for value in outcome.capacity_counts.iter_recorded() {
+        capacity_output.write(&[
+            (
+                "freq",
+                (value.value_iterated_to() as f64 / num_hit as f64 / 1_000_000.).into(),
+            ),
+            ("quantile", value.quantile().into()),
+        ])?
+    }
+    for (dimension, counts) in RESOURCE_DIMENSIONS.iter().zip(&outcome.resource_counts) {
+        for value in counts.iter_recorded() {
+            resource_output.write(&[
+                ("dimension", (*dimension).into()),
                 (
-                    a1 + a2,
-                    b1 + b2,
-                    c1.into_iter()
-                        .zip(c2)
-                        .map(|(n1, n2)| Class {
-                            num_node: n1.num_node + n2.num_node,
-                            capacity: n1.capacity + n2.capacity,
-                            hit_count: n1.hit_count + n2.hit_count,
-                        })
-                        .collect(),
+                    "freq",
+                    (value.value_iterated_to() as f64 / num_hit as f64 / 1_000_000.).into(),
+                ),
+                ("quantile", value.quantile().into()),
+            ])?
+        }
+    }
+    for (class, stats) in outcome.classes.into_iter().enumerate() {
+        // no hit is expected of a class without nodes, so it has no deviation either
+        if stats.num_node == 0 {
+            continue;
+        }
+        for (rank, hit_count) in stats.rank_hit_counts.iter().enumerate() {
+            rank_output.write(&[
+                ("class", class.into()),
+                ("rank", (rank + 1).into()),
+                ("rank_hit_count", (*hit_count).into()),
+            ])?
+        }
+        for (policy, read_count) in read_policies.iter().zip(&stats.read_counts) {
+            read_output.write(&[
+                ("read_policy", policy.name().into()),
+                ("class", class.into()),
+                ("num_class_node", stats.num_node.into()),
+                ("class_read_count", (*read_count).into()),
+            ])?
+        }
+        class_output.write(&[
+            ("class", class.into()),
+            ("num_class_node", stats.num_node.into()),
+            ("class_capacity", stats.capacity.into()),
+            ("class_bandwidth", stats.bandwidth.into()),
+            ("class_uptime", stats.uptime.into()),
+            ("class_hit_count", stats.hit_count.into()),
+            ("expected_hit_count", stats.expected_hit_count.into()),
+            (
+                "owned_hit_count",
+                (stats.ownership * num_find as f64).into(),
+            ),
+            (
+                "deviation",
+                (stats.hit_count as f64 / stats.expected_hit_count - 1.).into(),
+            ),
+        ])?
+    }
+    let mean = |fairness: &[Fairness], index: usize| {
+        fairness
+            .iter()
+            .map(|fairness| fairness.fields()[index].1)
+            .sum::<f64>()
+            / fairness.len() as f64
+    };
+    for (index, (metric, _)) in outcome.fairness[0].fields().into_iter().enumerate() {
+        fairness_output.write(&[
+            ("metric", metric.into()),
+            ("mean", mean(&outcome.fairness, index).into()),
+            // of the ownership, exact or sampled as in the `ownership` parameter, instead of the hits
+            (
+                "ownership_mean",
+                mean(&outcome.ownership_fairness, index).into(),
+            ),
+        ])?
+    }
+    outcome.samples.sort_unstable_by_key(|&(index, _)| index);
+    let series = (0..outcome.samples[0].1.len())
+        .map(|i| {
+            let (source, metric, _) = outcome.samples[0].1[i];
+            let samples = outcome
+                .samples
+                .iter()
+                .map(|(_, fields)| fields[i].2)
+                .collect();
+            ((source, metric), samples)
+        })
+        .collect::<Series>();
+    let mut rng = StdRng::seed_from_u64(seed);
+    for ((source, metric), samples) in &series {
+        let mut record = vec![("source", (*source).into()), ("metric", (*metric).into())];
+        record.extend(
+            Summary::new(samples, NUM_RESAMPLE, &mut rng)
+                .fields()
+                .into_iter()
+                .map(|(name, value)| (name, value.into())),
+        );
+        summary_output.write(&record)?
+    }
+    node_output.finish()?;
+    capacity_output.finish()?;
+    resource_output.finish()?;
+    class_output.finish()?;
+    rank_output.finish()?;
+    read_output.finish()?;
+    fairness_output.finish()?;
+    summary_output.finish()?;
+    Ok(series)
+}
+
+const RESOURCE_DIMENSIONS: [&str; 3] = ["storage", "bandwidth", "uptime"];
+
+// per node, for the sample at hand
+struct Load {
+    // the node is the `rank`th closest to the target, counting from 0
+    rank_hit_counts: Vec<u64>,
+    // the node serves the read under the `index`th policy
+    read_counts: Vec<u64>,
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+struct Class {
+    num_node: u64,
+    // sums of resources, which may not fit an integer with many classes
+    capacity: f64,
+    bandwidth: f64,
+    uptime: f64,
+    hit_count: u64,
+    // by `model::class_hit_rate`
+    expected_hit_count: f64,
+    // summed over the nodes and samples, by `keyspace::ownership` or `keyspace::sampled_ownership`
+    ownership: f64,
+    rank_hit_counts: Vec<u64>,
+    read_counts: Vec<u64>,
+}
+
+struct Outcome {
+    node_counts: Histogram<u64>,
+    // hit count per unit of storage, weighted by storage
+    capacity_counts: Histogram<u64>,
+    // the same for every dimension in `RESOURCE_DIMENSIONS`
+    resource_counts: [Histogram<u64>; 3],
+    classes: Vec<Class>,
+    fairness: Vec<Fairness>,
+    ownership_fairness: Vec<Fairness>,
+    // of every sample with its index, which are not saved in checkpoints but computed again from
+    // what is
+    samples: Vec<(usize, Vec<SampleField>)>,
+}
+
+const CHECKPOINT_MAGIC: &[u8; 4] = b"SFRQ";
+// bump on every change to the saved fields of `Outcome`
+const CHECKPOINT_VERSION: u32 = 2;
+
+// histogram weights are shares of the total of the dimension over the sample in this unit, so
+// that nodes keep their weight relative to each other whatever the spread of the capacities, and
+// the total weight of many samples stays far from overflowing
+const WEIGHT_SCALE: f64 = (1u64 << 40) as f64;
+
+impl Outcome {
+    fn empty(num_class: u8, find_size: usize, num_read_policy: usize) -> Self {
+        let class = Class {
+            rank_hit_counts: vec![0; find_size],
+            read_counts: vec![0; num_read_policy],
+            ..Default::default()
+        };
+        Self {
+            node_counts: Histogram::new(1).unwrap(),
+            capacity_counts: Histogram::new(1).unwrap(),
+            resource_counts: std::array::from_fn(|_| Histogram::new(1).unwrap()),
+            classes: vec![class; num_class as _],
+            fairness: Default::default(),
+            ownership_fairness: Default::default(),
+            samples: Default::default(),
+        }
+    }
+
+    fn new(
+        num_class: u8,
+        find_size: usize,
+        num_read_policy: usize,
+        nodes: &[snapshot::Node],
+        loads: &HashMap<NodeId, Load>,
+        ownership: &FxHashMap<NodeId, f64>,
+    ) -> Self {
+        let mut outcome = Self::empty(num_class, find_size, num_read_policy);
+        let mut totals = [0.; 3];
+        for node in nodes {
+            for (total, (_, value)) in totals.iter_mut().zip(node.resources.dimensions()) {
+                *total += value
+            }
+        }
+        let weight =
+            |value: f64, total: f64| ((value / total * WEIGHT_SCALE).round() as u64).max(1);
+        for node in nodes {
+            let load = &loads[&node.id];
+            let hit_count = load.rank_hit_counts.iter().sum::<u64>();
+            let resources = &node.resources;
+            outcome.node_counts.record(hit_count).unwrap();
+            outcome
+                .capacity_counts
+                .record_n(
+                    hit_count * 1_000_000 / resources.storage,
+                    weight(resources.storage as _, totals[0]),
                 )
-            },
+                .unwrap();
+            for (((_, value), total), counts) in resources
+                .dimensions()
+                .into_iter()
+                .zip(totals)
+                .zip(&mut outcome.resource_counts)
+            {
+                counts
+                    .record_n(
+                        (hit_count as f64 * 1_000_000. / value) as _,
+                        weight(value, total),
+                    )
+                    .unwrap()
+            }
+            let class = &mut outcome.classes[node.class as usize];
+            class.num_node += 1;
+            class.capacity += resources.storage as f64;
+            class.bandwidth += resources.bandwidth as f64;
+            class.uptime += resources.uptime;
+            class.hit_count += hit_count;
+            class.ownership += ownership[&node.id];
+            add(&mut class.rank_hit_counts, &load.rank_hit_counts);
+            add(&mut class.read_counts, &load.read_counts)
+        }
+        outcome.fairness.push(Fairness::new(
+            &nodes
+                .iter()
+                .map(|node| {
+                    let hit_count = loads[&node.id].rank_hit_counts.iter().sum();
+                    (hit_count, node.resources.storage)
+                })
+                .collect::<Vec<_>>(),
+        ));
+        outcome.ownership_fairness.push(Fairness::from_shares(
+            &nodes
+                .iter()
+                .map(|node| (ownership[&node.id], node.resources.storage as f64))
+                .collect::<Vec<_>>(),
+        ));
+        outcome
+    }
+
+    fn expect(&mut self, classified: bool, num_find: u32, find_size: usize) {
+        // vanilla overlay ranks every node as if it were in class 0
+        let num_nodes = self
+            .classes
+            .iter()
+            .map(|class| class.num_node as usize)
+            .collect::<Vec<_>>();
+        let rates = if classified {
+            model::class_hit_rate(&num_nodes, find_size)
+        } else {
+            let rate = model::class_hit_rate(&[num_nodes.iter().sum()], find_size)[0];
+            vec![rate; num_nodes.len()]
+        };
+        for (class, rate) in self.classes.iter_mut().zip(rates) {
+            class.expected_hit_count = num_find as f64 * class.num_node as f64 * rate
+        }
+    }
+
+    // for the outcome of a single sample
+    fn record_sample(&mut self, index: usize, num_hit: u64) {
+        let freq = |hit_count: u64| hit_count as f64 / num_hit as f64;
+        let mut fields = vec![
+            (
+                "hit",
+                "freq_p50",
+                freq(self.node_counts.value_at_quantile(0.5)),
+            ),
+            (
+                "hit",
+                "freq_p90",
+                freq(self.node_counts.value_at_quantile(0.9)),
+            ),
+            (
+                "hit",
+                "freq_p99",
+                freq(self.node_counts.value_at_quantile(0.99)),
+            ),
+            ("hit", "freq_max", freq(self.node_counts.max())),
+        ];
+        fields.extend(
+            self.fairness[0]
+                .fields()
+                .map(|(metric, value)| ("hit", metric, value)),
+        );
+        // without sampling noise of the targets, but still of the population
+        fields.extend(
+            self.ownership_fairness[0]
+                .fields()
+                .map(|(metric, value)| ("ownership", metric, value)),
+        );
+        self.samples.push((index, fields))
+    }
+
+    fn histograms(&self) -> impl Iterator<Item = &Histogram<u64>> {
+        [&self.node_counts, &self.capacity_counts]
+            .into_iter()
+            .chain(&self.resource_counts)
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        // written aside and renamed, so a run killed while saving leaves no partial checkpoint
+        let partial_path = path.with_extension("partial");
+        let mut writer = BufWriter::new(File::create(&partial_path)?);
+        writer.write_all(CHECKPOINT_MAGIC)?;
+        writer.write_all(&CHECKPOINT_VERSION.to_le_bytes())?;
+        let mut serializer = V2Serializer::new();
+        for counts in self.histograms() {
+            serializer.serialize(counts, &mut writer)?;
+        }
+        bincode::serde::encode_into_std_write(
+            (&self.classes, &self.fairness, &self.ownership_fairness),
+            &mut writer,
+            bincode::config::standard(),
+        )?;
+        writer.flush()?;
+        drop(writer);
+        rename(partial_path, path)?;
+        Ok(())
+    }
+
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        ensure!(&magic == CHECKPOINT_MAGIC, "not a checkpoint file");
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        ensure!(
+            version == CHECKPOINT_VERSION,
+            "unsupported checkpoint version {version} (expect {CHECKPOINT_VERSION})"
         );
-    eprintln!();
-
-    for value in node_counts.iter_recorded() {
-        writeln!(
-            &mut node_output,
-            "{prefix},{},{}",
-            value.value_iterated_to() as f32 / (num_find * find_size as u32) as f32,
-            value.quantile()
-        )?
-    }
-    for value in capacity_counts.iter_recorded() {
-        writeln!(
-            &mut capacity_output,
-            "{prefix},{},{}",
-            value.value_iterated_to() as f32 / (num_find * find_size as u32) as f32 / 1_000_000.,
-            value.quantile()
-        )?
-    }
-    for (class, stats) in classes.into_iter().enumerate() {
-        writeln!(
-            &mut class_output,
-            "{prefix},{class},{},{},{}",
-            stats.num_node, stats.capacity, stats.hit_count
-        )?
-    }
-    Ok(())
+        let mut deserializer = Deserializer::new();
+        let mut histogram = || -> anyhow::Result<Histogram<u64>> {
+            let mut counts = deserializer.deserialize(&mut reader)?;
+            // merged with histograms of any range later
+            counts.auto(true);
+            Ok(counts)
+        };
+        let node_counts = histogram()?;
+        let capacity_counts = histogram()?;
+        let resource_counts = [histogram()?, histogram()?, histogram()?];
+        let (classes, fairness, ownership_fairness) =
+            bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard())?;
+        Ok(Self {
+            node_counts,
+            capacity_counts,
+            resource_counts,
+            classes,
+            fairness,
+            ownership_fairness,
+            samples: Default::default(),
+        })
+    }
+
+    fn merge(mut self, other: Self) -> Self {
+        self.node_counts += other.node_counts;
+        self.capacity_counts += other.capacity_counts;
+        for (counts, other_counts) in self.resource_counts.iter_mut().zip(other.resource_counts) {
+            *counts += other_counts
+        }
+        for (class, other_class) in self.classes.iter_mut().zip(other.classes) {
+            class.num_node += other_class.num_node;
+            class.capacity += other_class.capacity;
+            class.bandwidth += other_class.bandwidth;
+            class.uptime += other_class.uptime;
+            class.hit_count += other_class.hit_count;
+            class.expected_hit_count += other_class.expected_hit_count;
+            class.ownership += other_class.ownership;
+            add(&mut class.rank_hit_counts, &other_class.rank_hit_counts);
+            add(&mut class.read_counts, &other_class.read_counts)
+        }
+        self.fairness.extend(other.fairness);
+        self.ownership_fairness.extend(other.ownership_fairness);
+        self.samples.extend(other.samples);
+        self
+    }
+}
+
+fn add(counts: &mut [u64], other_counts: &[u64]) {
+    for (count, other_count) in counts.iter_mut().zip(other_counts) {
+        *count += other_count
+    }
 }
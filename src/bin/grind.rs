@@ -0,0 +1,68 @@
+use std::{fs::create_dir_all, time::Instant, time::UNIX_EPOCH};
+
+use rand::{Rng, SeedableRng, rng, rngs::StdRng};
+use storage_simulation::{
+    adversary::Position,
+    classified, identity,
+    output::{Format, Manifest},
+};
+
+// how close keyed grinding gets an id to a named object under each class, against the closed
+// form `adversary::Position` samples from, and what it costs in hashes
+fn main() -> anyhow::Result<()> {
+    let seed = match std::env::var("GRIND_SEED") {
+        Ok(seed) => seed.parse()?,
+        Err(_) => rng().random(),
+    };
+    let format = match std::env::var("GRIND_FORMAT") {
+        Ok(format) => format.parse()?,
+        Err(_) => Format::Csv,
+    };
+    create_dir_all("data/grind")?;
+    let num_target = 100usize;
+    let manifest = Manifest::new(seed).param("num_target", num_target);
+    let tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
+    let mut output = format.create(format!("data/grind/{tag}"), &manifest)?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let targets = (0..num_target)
+        .map(|index| identity::name_target(&format!("object-{index}")))
+        .collect::<Vec<_>>();
+    for class in [0, 4, 7] {
+        for grind in [1, 1 << 4, 1 << 8, 1 << 12, 1 << 16] {
+            eprintln!("Class {class} Grind {grind}");
+            // distances relative to the largest one the class can see
+            let scale = (!0u64 >> class) as f64 + 1.;
+            let start = Instant::now();
+            let hashed_distance = targets
+                .iter()
+                .map(|&target| {
+                    let (_, node_id) = identity::grind(target, class, grind, &mut rng);
+                    classified::distance(node_id, target, class) as f64 / scale
+                })
+                .sum::<f64>()
+                / num_target as f64;
+            let hash_rate = (grind as usize * num_target) as f64 / start.elapsed().as_secs_f64();
+            let position = Position::Targeted {
+                targets: targets.clone(),
+                grind,
+            };
+            let modelled_distance = (0..num_target)
+                .map(|index| {
+                    let node_id = position.node_id(index, class, &mut rng);
+                    classified::distance(node_id, targets[index], class) as f64 / scale
+                })
+                .sum::<f64>()
+                / num_target as f64;
+            output.write(&[
+                ("class", class.into()),
+                ("grind", grind.into()),
+                ("hashed_distance", hashed_distance.into()),
+                ("modelled_distance", modelled_distance.into()),
+                // mean of the least of `grind` uniform values on [0, 1)
+                ("expected_distance", (1. / (grind as f64 + 1.)).into()),
+                ("hash_rate", hash_rate.into()),
+            ])?
+        }
+    }
+    output.finish()
+}
@@ -0,0 +1,302 @@
+use std::{
+    fs::{create_dir_all, read_dir},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context, bail};
+use plotters::prelude::*;
+use rustc_hash::FxHashMap;
+use storage_simulation::output::{Format, Record, Value, read_records};
+
+// a quick look at the results of `freq` runs without leaving the crate: the CDF of the hits of
+// the nodes and of the hits per unit of storage, and the share of the hits of every class next to
+// its share of the capacity, drawn to SVG
+fn main() -> anyhow::Result<()> {
+    // comma separated runs, as the path of their output without the suffix, e.g.
+    // data/freq/1700000000. by default the latest run of every strategy
+    let runs = match std::env::var("PLOT_RUN") {
+        Ok(runs) => runs.split(',').map(PathBuf::from).collect(),
+        Err(_) => latest_runs("data/freq")?,
+    };
+    if runs.is_empty() {
+        bail!("no run to plot")
+    }
+    let runs = runs
+        .iter()
+        .map(|run| Run::load(run))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    create_dir_all("data/plot")?;
+    let tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
+    plot_cdf(
+        format!("data/plot/{tag}-node.svg"),
+        "Hit frequency of nodes",
+        "share of the hits",
+        &runs
+            .iter()
+            .map(|run| (run.label.clone(), run.node.clone()))
+            .collect::<Vec<_>>(),
+    )?;
+    plot_cdf(
+        format!("data/plot/{tag}-capacity.svg"),
+        "Hit frequency per unit of storage, weighted by storage",
+        "share of the hits per unit",
+        &runs
+            .iter()
+            .map(|run| (run.label.clone(), run.capacity.clone()))
+            .collect::<Vec<_>>(),
+    )?;
+    // the populations are the same across strategies when run with the same seed, so the
+    // capacity of the first run stands for all of them
+    let mut shares = vec![("capacity".to_string(), runs[0].capacity_share.clone())];
+    shares.extend(
+        runs.iter()
+            .map(|run| (run.label.clone(), run.hit_share.clone())),
+    );
+    plot_class(format!("data/plot/{tag}-class.svg"), &shares)?;
+    eprintln!("Plotted {} run(s) to data/plot/{tag}-*.svg", runs.len());
+    Ok(())
+}
+
+struct Run {
+    label: String,
+    // (frequency, quantile), the points of the CDF
+    node: Vec<(f64, f64)>,
+    capacity: Vec<(f64, f64)>,
+    // indexed by class, 0 for classes without nodes, which have no output
+    hit_share: Vec<f64>,
+    capacity_share: Vec<f64>,
+}
+
+impl Run {
+    fn load(run: &Path) -> anyhow::Result<Self> {
+        let cdf = |records: Vec<Record>| {
+            records
+                .iter()
+                .map(|record| Ok((number(record, "freq")?, number(record, "quantile")?)))
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        let node = read(run, "node")?;
+        let strategy = match node.first() {
+            Some(record) => field(record, "strategy")?
+                .as_str()
+                .context("strategy is not a string")?
+                .to_string(),
+            None => bail!("empty node output of run {}", run.display()),
+        };
+        let name = run.file_name().unwrap_or_default().to_string_lossy();
+        let classes = read(run, "class")?
+            .iter()
+            .map(|record| {
+                Ok((
+                    number(record, "class")? as usize,
+                    number(record, "class_hit_count")?,
+                    number(record, "class_capacity")?,
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let num_class = classes
+            .iter()
+            .map(|&(class, ..)| class + 1)
+            .max()
+            .unwrap_or(0);
+        let share = |values: Vec<(usize, f64)>| {
+            let total = values.iter().map(|&(_, value)| value).sum::<f64>();
+            let mut shares = vec![0.; num_class];
+            for (class, value) in values {
+                shares[class] = value / total
+            }
+            shares
+        };
+        Ok(Self {
+            label: format!("{strategy} {name}"),
+            node: cdf(node)?,
+            capacity: cdf(read(run, "capacity")?)?,
+            hit_share: share(
+                classes
+                    .iter()
+                    .map(|&(class, hit, _)| (class, hit))
+                    .collect(),
+            ),
+            capacity_share: share(
+                classes
+                    .iter()
+                    .map(|&(class, _, capacity)| (class, capacity))
+                    .collect(),
+            ),
+        })
+    }
+}
+
+// the output of `kind` of a run, in whichever format it has been written
+fn read(run: &Path, kind: &str) -> anyhow::Result<Vec<Record>> {
+    for format in [Format::Csv, Format::JsonLines] {
+        let mut path = run.as_os_str().to_owned();
+        path.push(format!("-{kind}.{}", format.extension()));
+        if Path::new(&path).exists() {
+            return read_records(path);
+        }
+    }
+    bail!("no {kind} output of run {}", run.display())
+}
+
+fn field<'a>(record: &'a Record, name: &str) -> anyhow::Result<&'a Value> {
+    record
+        .iter()
+        .find(|(field, _)| field == name)
+        .map(|(_, value)| value)
+        .with_context(|| format!("no field {name}"))
+}
+
+fn number(record: &Record, name: &str) -> anyhow::Result<f64> {
+    field(record, name)?
+        .as_f64()
+        .with_context(|| format!("field {name} is not a number"))
+}
+
+// the latest run of every strategy in `dir`, oldest first
+fn latest_runs(dir: impl AsRef<Path>) -> anyhow::Result<Vec<PathBuf>> {
+    let mut latest = FxHashMap::<String, (u64, PathBuf)>::default();
+    for entry in read_dir(&dir)? {
+        let file_name = entry?.file_name();
+        let Some((tag, suffix)) = file_name.to_str().and_then(|name| name.split_once('-')) else {
+            continue;
+        };
+        let Ok(tag) = tag.parse::<u64>() else {
+            continue;
+        };
+        if !suffix.starts_with("node.") {
+            continue;
+        }
+        let run = dir.as_ref().join(tag.to_string());
+        let Some(record) = read(&run, "node")?.into_iter().next() else {
+            continue;
+        };
+        let strategy = field(&record, "strategy")?.as_str().unwrap_or_default();
+        if latest
+            .get(strategy)
+            .is_none_or(|&(latest_tag, _)| latest_tag < tag)
+        {
+            latest.insert(strategy.into(), (tag, run));
+        }
+    }
+    let mut runs = latest.into_values().collect::<Vec<_>>();
+    runs.sort_unstable();
+    Ok(runs.into_iter().map(|(_, run)| run).collect())
+}
+
+// on a log scale, as the frequencies of classes are orders of magnitude apart. nodes without hits
+// are left out, so a line starts at the share of them
+fn plot_cdf(
+    path: String,
+    caption: &str,
+    x_desc: &str,
+    series: &[(String, Vec<(f64, f64)>)],
+) -> anyhow::Result<()> {
+    let points = || {
+        series
+            .iter()
+            .flat_map(|(_, points)| points)
+            .filter(|&&(freq, _)| freq > 0.)
+    };
+    let min = points()
+        .map(|&(freq, _)| freq)
+        .fold(f64::INFINITY, f64::min);
+    let max = points().map(|&(freq, _)| freq).fold(0., f64::max);
+    if min > max {
+        bail!("nothing to plot for {caption}")
+    }
+    let root = SVGBackend::new(&path, (800, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(caption, ("sans-serif", 20))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(((min / 2.)..(max * 2.)).log_scale(), 0f64..1.)?;
+    chart
+        .configure_mesh()
+        .x_desc(x_desc)
+        .y_desc("quantile")
+        .x_label_formatter(&|freq| format!("{freq:.0e}"))
+        .draw()?;
+    for (index, (label, points)) in series.iter().enumerate() {
+        let color = Palette99::pick(index).to_rgba();
+        chart
+            .draw_series(LineSeries::new(
+                points.iter().copied().filter(|&(freq, _)| freq > 0.),
+                color.stroke_width(2),
+            ))?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::LowerRight)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    root.present()?;
+    Ok(())
+}
+
+// bars of every series side by side for each class
+fn plot_class(path: String, series: &[(String, Vec<f64>)]) -> anyhow::Result<()> {
+    let num_class = series
+        .iter()
+        .map(|(_, shares)| shares.len())
+        .max()
+        .unwrap_or(0);
+    let max = series
+        .iter()
+        .flat_map(|(_, shares)| shares)
+        .copied()
+        .fold(0., f64::max);
+    if num_class == 0 || max == 0. {
+        bail!("nothing to plot for classes")
+    }
+    let root = SVGBackend::new(&path, (800, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(
+            "Share of the hits and of the capacity per class",
+            ("sans-serif", 20),
+        )
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(-0.5..num_class as f64 - 0.5, 0f64..max * 1.1)?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_labels(num_class + 1)
+        .x_label_formatter(&|x| {
+            if (x - x.round()).abs() < 1e-6 {
+                format!("{}", x.round())
+            } else {
+                String::new()
+            }
+        })
+        .x_desc("class")
+        .y_desc("share")
+        .draw()?;
+    let width = 0.8 / series.len() as f64;
+    for (index, (label, shares)) in series.iter().enumerate() {
+        let color = Palette99::pick(index).to_rgba();
+        chart
+            .draw_series(shares.iter().enumerate().map(|(class, &share)| {
+                let left = class as f64 - 0.4 + width * index as f64;
+                Rectangle::new([(left, 0.), (left + width, share)], color.filled())
+            }))?
+            .label(label)
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 10, y + 5)], color.filled()));
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperRight)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    root.present()?;
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use std::{fs::create_dir_all, time::UNIX_EPOCH};
+
+use rand::{Rng, SeedableRng, rng, rngs::StdRng};
+use rand_distr::Distribution;
+use storage_simulation::{
+    BinOverlay, Classified, NodeId, Overlay,
+    classified::{self, capacity_class},
+    keyspace,
+    output::{Format, Manifest, RecordWriter},
+    resource::capacity_distr,
+};
+
+// how much of the stored data moves when a single node joins or leaves, computed exactly over
+// the key space and checked against random targets
+fn main() -> anyhow::Result<()> {
+    let seed = match std::env::var("REBALANCE_SEED") {
+        Ok(seed) => seed.parse()?,
+        Err(_) => rng().random(),
+    };
+    let format = match std::env::var("REBALANCE_FORMAT") {
+        Ok(format) => format.parse()?,
+        Err(_) => Format::Csv,
+    };
+    create_dir_all("data/rebalance")?;
+    let (num_node, num_event, find_size, num_class, skew, num_target) =
+        (10_000usize, 200usize, 3usize, 8u8, 1., 10_000usize);
+    let manifest = Manifest::new(seed)
+        .param("num_node", num_node)
+        .param("num_event", num_event)
+        .param("find_size", find_size)
+        .param("num_class", num_class)
+        .param("skew", skew)
+        .param("num_target", num_target);
+    let tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
+    let mut output = format.create(format!("data/rebalance/{tag}"), &manifest)?;
+    for classified in [false, true] {
+        run(
+            classified,
+            num_node,
+            num_event,
+            find_size,
+            num_class,
+            skew,
+            num_target,
+            seed,
+            &mut *output,
+        )?
+    }
+    output.finish()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run(
+    classified: bool,
+    num_node: usize,
+    num_event: usize,
+    find_size: usize,
+    num_class: u8,
+    skew: f32,
+    num_target: usize,
+    seed: u64,
+    output: &mut dyn RecordWriter,
+) -> anyhow::Result<()> {
+    let strategy = if classified { "Classified" } else { "Vanilla" };
+    eprintln!("{strategy} Number of node {num_node} Seed {seed}");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let capacity_distr = capacity_distr(num_class, skew)?;
+    // (node id, capacity)
+    let mut nodes = Vec::<(NodeId, u64)>::new();
+    let mut overlay = if classified {
+        Overlay::Classified(Classified::new())
+    } else {
+        Overlay::Vanilla(BinOverlay::new())
+    };
+    let class_of = |capacity| {
+        if classified {
+            capacity_class(capacity)
+        } else {
+            0
+        }
+    };
+    for _ in 0..num_node {
+        let node_id = rng.random();
+        let capacity = capacity_distr.sample(&mut rng) as u64;
+        overlay.try_insert_node(node_id, class_of(capacity))?;
+        nodes.push((node_id, capacity))
+    }
+    overlay.optimize();
+
+    for index in 0..num_event {
+        // joins and leaves take turns, so the population stays around its size
+        let join = index % 2 == 0;
+        let before = nodes
+            .iter()
+            .map(|&(node_id, capacity)| (node_id, class_of(capacity)))
+            .collect::<Vec<classified::NodeId>>();
+        let mut after_overlay = overlay.clone();
+        let total_capacity = nodes.iter().map(|&(_, capacity)| capacity).sum::<u64>();
+        let (node_id, capacity) = if join {
+            let node = (rng.random(), capacity_distr.sample(&mut rng) as u64);
+            after_overlay.try_insert_node(node.0, class_of(node.1))?;
+            nodes.push(node);
+            node
+        } else {
+            let node = nodes.swap_remove(rng.random_range(0..nodes.len()));
+            after_overlay.remove_node(node.0, class_of(node.1));
+            node
+        };
+        after_overlay.optimize();
+        let after = nodes
+            .iter()
+            .map(|&(node_id, capacity)| (node_id, class_of(capacity)))
+            .collect::<Vec<_>>();
+        let change = keyspace::change(&before, &after, find_size);
+        let sampled =
+            keyspace::sampled_change(&overlay, &after_overlay, find_size, num_target, &mut rng);
+        output.write(&[
+            ("strategy", strategy.into()),
+            ("event", if join { "join" } else { "leave" }.into()),
+            ("node_id", node_id.into()),
+            ("class", capacity_class(capacity).into()),
+            // the least a scheme can move to keep the load proportional to capacity
+            (
+                "capacity_share",
+                (capacity as f64 / (total_capacity + if join { capacity } else { 0 }) as f64)
+                    .into(),
+            ),
+            ("changed", change.changed.into()),
+            ("moved", change.moved.into()),
+            ("sampled_changed", sampled.changed.into()),
+            ("sampled_moved", sampled.moved.into()),
+        ])?;
+        overlay = after_overlay
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use std::{fs::create_dir_all, time::UNIX_EPOCH};
+
+use rand::{Rng, SeedableRng, rng, rngs::StdRng};
+use rand_distr::Distribution;
+use rustc_hash::FxHashSet;
+use storage_simulation::{
+    BinOverlay, Classified, Overlay, Target,
+    adversary::{Claim, Position, num_captured},
+    classified::capacity_class,
+    output::{Format, Manifest, RecordWriter},
+    resource::{capacity_distr, max_capacity},
+};
+
+fn main() -> anyhow::Result<()> {
+    let seed = match std::env::var("SYBIL_SEED") {
+        Ok(seed) => seed.parse()?,
+        Err(_) => rng().random(),
+    };
+    let format = match std::env::var("SYBIL_FORMAT") {
+        Ok(format) => format.parse()?,
+        Err(_) => Format::Csv,
+    };
+    create_dir_all("data/sybil")?;
+    let (num_node, find_size, num_class, skew, num_target) = (10_000, 3, 8, 1., 100_000);
+    let manifest = Manifest::new(seed)
+        .param("num_node", num_node)
+        .param("find_size", find_size)
+        .param("num_class", num_class)
+        .param("skew", skew)
+        .param("num_target", num_target);
+    let tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
+    let mut output = format.create(format!("data/sybil/{tag}"), &manifest)?;
+    for classified in [false, true] {
+        for budget in [Budget::Node, Budget::Capacity] {
+            for claim in [Claim::Honest, Claim::Inflate(num_class - 1)] {
+                for (position, num_attacked) in [("random", 0), ("targeted", 100), ("eclipse", 1)] {
+                    for fraction in [0.01, 0.02, 0.05, 0.1, 0.2, 0.3] {
+                        let scenario = Scenario {
+                            classified,
+                            budget,
+                            claim,
+                            position,
+                            num_attacked,
+                            fraction,
+                        };
+                        run(
+                            &scenario,
+                            num_node,
+                            find_size,
+                            num_class,
+                            skew,
+                            num_target,
+                            seed,
+                            &mut *output,
+                        )?
+                    }
+                }
+            }
+        }
+    }
+    output.finish()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Budget {
+    // the adversary runs `fraction` of all nodes
+    Node,
+    // the adversary owns `fraction` of all capacity. honestly claimed, it is spent on nodes of the
+    // largest capacity. inflated, it is spread over sybils of the smallest capacity
+    Capacity,
+}
+
+struct Scenario {
+    classified: bool,
+    budget: Budget,
+    claim: Claim,
+    position: &'static str,
+    // number of targets the adversary aims at, none for random position
+    num_attacked: usize,
+    fraction: f64,
+}
+
+// ids ground per adversarial node when it aims at a target
+const GRIND: u32 = 1 << 16;
+
+#[allow(clippy::too_many_arguments)]
+fn run(
+    scenario: &Scenario,
+    num_node: usize,
+    find_size: usize,
+    num_class: u8,
+    skew: f32,
+    num_target: usize,
+    seed: u64,
+    output: &mut dyn RecordWriter,
+) -> anyhow::Result<()> {
+    let strategy = if scenario.classified {
+        "Classified"
+    } else {
+        "Vanilla"
+    };
+    let mut rng = StdRng::seed_from_u64(seed);
+    let max_capacity = max_capacity(num_class);
+    let capacity_distr = capacity_distr(num_class, skew)?;
+    let mut overlay = if scenario.classified {
+        Overlay::Classified(Classified::new())
+    } else {
+        Overlay::Vanilla(BinOverlay::new())
+    };
+    let mut honest_capacity = 0;
+    for _ in 0..num_node {
+        let capacity = capacity_distr.sample(&mut rng) as u64;
+        honest_capacity += capacity;
+        overlay.insert_node(rng.random(), capacity_class(capacity))
+    }
+
+    // adversarial share of the total after joining
+    let scale = scenario.fraction / (1. - scenario.fraction);
+    let (num_adversary, adversary_capacity) = match (scenario.budget, scenario.claim) {
+        (Budget::Node, _) => ((num_node as f64 * scale) as usize, None),
+        (Budget::Capacity, Claim::Honest) => (
+            (honest_capacity as f64 * scale / max_capacity as f64) as usize,
+            Some(max_capacity),
+        ),
+        (Budget::Capacity, Claim::Inflate(_)) => {
+            ((honest_capacity as f64 * scale) as usize, Some(1))
+        }
+    };
+    let attacked = (0..scenario.num_attacked)
+        .map(|_| rng.random())
+        .collect::<Vec<Target>>();
+    let position = if attacked.is_empty() {
+        Position::Random
+    } else {
+        Position::Targeted {
+            targets: attacked.clone(),
+            grind: GRIND,
+        }
+    };
+    let mut adversary = FxHashSet::default();
+    for index in 0..num_adversary {
+        let capacity = match adversary_capacity {
+            Some(capacity) => capacity,
+            None => capacity_distr.sample(&mut rng) as _,
+        };
+        let class = match scenario.claim {
+            Claim::Honest => capacity_class(capacity),
+            Claim::Inflate(class) => class,
+        };
+        let rank_class = if scenario.classified { class } else { 0 };
+        let node_id = position.node_id(index, rank_class, &mut rng);
+        overlay.insert_node(node_id, class);
+        adversary.insert(node_id);
+    }
+    overlay.optimize();
+
+    // (fraction of targets whose whole find set is adversarial, mean adversarial share of find sets)
+    let measure = |targets: &[Target]| {
+        if targets.is_empty() {
+            return (f64::NAN, f64::NAN);
+        }
+        let (mut num_full, mut num_captured_node) = (0, 0);
+        for &target in targets {
+            let num_captured = num_captured(&overlay, &adversary, target, find_size);
+            if num_captured == find_size {
+                num_full += 1
+            }
+            num_captured_node += num_captured
+        }
+        (
+            num_full as f64 / targets.len() as f64,
+            num_captured_node as f64 / (targets.len() * find_size) as f64,
+        )
+    };
+    let random_targets = (0..num_target).map(|_| rng.random()).collect::<Vec<_>>();
+    let (captured_random, share_random) = measure(&random_targets);
+    let (captured_attacked, share_attacked) = measure(&attacked);
+    eprintln!(
+        "{strategy} {:?} {:?} {} {} captured {captured_random} {captured_attacked}",
+        scenario.budget, scenario.claim, scenario.position, scenario.fraction
+    );
+    output.write(&[
+        ("strategy", strategy.into()),
+        (
+            "budget",
+            match scenario.budget {
+                Budget::Node => "node",
+                Budget::Capacity => "capacity",
+            }
+            .into(),
+        ),
+        (
+            "claim",
+            match scenario.claim {
+                Claim::Honest => "honest",
+                Claim::Inflate(_) => "inflate",
+            }
+            .into(),
+        ),
+        ("position", scenario.position.into()),
+        ("num_attacked", scenario.num_attacked.into()),
+        ("fraction", scenario.fraction.into()),
+        ("num_adversary", num_adversary.into()),
+        ("captured_random", captured_random.into()),
+        ("share_random", share_random.into()),
+        ("captured_attacked", captured_attacked.into()),
+        ("share_attacked", share_attacked.into()),
+    ])
+}
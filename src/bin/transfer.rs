@@ -0,0 +1,187 @@
+use std::{collections::HashMap, fs::create_dir_all, time::UNIX_EPOCH};
+
+use hdrhistogram::Histogram;
+use rand::{Rng, SeedableRng, rng, rngs::StdRng};
+use rand_distr::Distribution;
+use storage_simulation::{
+    BinOverlay, Classified, Overlay,
+    classified::capacity_class,
+    network::{Coordinate, LatencyMatrix, Link, Location, NetworkModel, Transfer},
+    output::{Format, Manifest},
+    resource::capacity_distr,
+};
+
+fn main() -> anyhow::Result<()> {
+    let seed = match std::env::var("TRANSFER_SEED") {
+        Ok(seed) => seed.parse()?,
+        Err(_) => rng().random(),
+    };
+    let format = match std::env::var("TRANSFER_FORMAT") {
+        Ok(format) => format.parse()?,
+        Err(_) => Format::Csv,
+    };
+    // without a matrix, nodes are placed in a synthetic coordinate space
+    let matrix = match std::env::var("TRANSFER_LATENCY_MATRIX") {
+        Ok(path) => Some(LatencyMatrix::load(path)?),
+        Err(_) => None,
+    };
+    create_dir_all("data/transfer")?;
+    for classified in [false, true] {
+        run(
+            classified,
+            10_000,
+            100,
+            1_000,
+            3,
+            4 << 20,
+            8,
+            1.,
+            matrix.clone(),
+            seed,
+            format,
+        )?
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run(
+    classified: bool,
+    num_node: usize,
+    num_round: usize,
+    num_concurrent: usize,
+    find_size: usize,
+    object_size: u64,
+    num_class: u8,
+    skew: f32,
+    matrix: Option<LatencyMatrix>,
+    seed: u64,
+    format: Format,
+) -> anyhow::Result<()> {
+    eprintln!("Number of node {num_node} Number of class {num_class} Skew {skew} Seed {seed}");
+    let strategy = if classified { "Classified" } else { "Vanilla" };
+    let manifest = Manifest::new(seed)
+        .param("strategy", strategy)
+        .param("num_node", num_node)
+        .param("num_round", num_round)
+        .param("num_concurrent", num_concurrent)
+        .param("find_size", find_size)
+        .param("object_size", object_size)
+        .param("num_class", num_class)
+        .param("skew", skew)
+        .param(
+            "latency",
+            if matrix.is_some() {
+                "matrix"
+            } else {
+                "synthetic"
+            },
+        );
+    let tag = UNIX_EPOCH.elapsed().unwrap().as_secs();
+    // a run takes less than a second, so the tag alone does not tell the strategies apart
+    let prefix = format!("data/transfer/{tag}-{}", strategy.to_lowercase());
+    let mut latency_output = format.create(format!("{prefix}-latency"), &manifest)?;
+    let mut class_output = format.create(format!("{prefix}-class"), &manifest)?;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let capacity_distr = capacity_distr(num_class, skew)?;
+    let mut overlay = if classified {
+        Overlay::Classified(Classified::new())
+    } else {
+        Overlay::Vanilla(BinOverlay::new())
+    };
+    let mut network = NetworkModel::new(matrix);
+    let mut node_ids = Vec::new();
+    let mut node_classes = Vec::new();
+    for _ in 0..num_node {
+        let node_id = rng.random();
+        let class = capacity_class(capacity_distr.sample(&mut rng) as _);
+        overlay.insert_node(node_id, class);
+        let location = match network.matrix() {
+            Some(matrix) => Location::Site(rng.random_range(0..matrix.num_site())),
+            None => Location::Coordinate(Coordinate::synthetic(&mut rng)),
+        };
+        // 1 to 100 MB/s upload, log-uniform and independent of storage capacity
+        let upload = 10f64.powf(rng.random_range(6.0..8.0));
+        network.insert_node(
+            node_id,
+            Link {
+                location,
+                upload,
+                download: upload * 10.,
+            },
+        )?;
+        node_ids.push(node_id);
+        node_classes.push((node_id, class))
+    }
+    overlay.optimize();
+    let node_classes = node_classes.into_iter().collect::<HashMap<_, _>>();
+
+    // in microsecond
+    let mut retrieval_latency = Histogram::<u64>::new(3)?;
+    let mut completion_time = Histogram::<u64>::new(3)?;
+    #[derive(Default, Clone)]
+    struct Class {
+        num_node: u64,
+        upload: f64,
+        num_served: u64,
+        serve_time: f64,
+    }
+    let mut classes = vec![Class::default(); num_class as _];
+    for (&node_id, &class) in &node_classes {
+        let class = &mut classes[class as usize];
+        class.num_node += 1;
+        class.upload += network.link(node_id).upload
+    }
+    for _ in 0..num_round {
+        let transfers = (0..num_concurrent)
+            .map(|_| {
+                let client = node_ids[rng.random_range(0..node_ids.len())];
+                let replicas = overlay.find(rng.random(), find_size);
+                Transfer {
+                    client,
+                    server: network.nearest(client, &replicas).unwrap(),
+                    size: object_size as _,
+                }
+            })
+            .collect::<Vec<_>>();
+        for (transfer, time) in transfers.iter().zip(network.complete(&transfers)) {
+            retrieval_latency.record((network.rtt(transfer.client, transfer.server) * 1e6) as _)?;
+            completion_time.record((time * 1e6) as _)?;
+            let class = &mut classes[node_classes[&transfer.server] as usize];
+            class.num_served += 1;
+            class.serve_time += time
+        }
+    }
+
+    for (metric, histogram) in [
+        ("retrieval_latency", &retrieval_latency),
+        ("completion_time", &completion_time),
+    ] {
+        for quantile in [0.1, 0.5, 0.9, 0.99, 0.999] {
+            latency_output.write(&[
+                ("metric", metric.into()),
+                ("quantile", quantile.into()),
+                (
+                    "value",
+                    (histogram.value_at_quantile(quantile) as f64 / 1e6).into(),
+                ),
+            ])?
+        }
+    }
+    for (class, stats) in classes.into_iter().enumerate() {
+        class_output.write(&[
+            ("class", class.into()),
+            ("num_class_node", stats.num_node.into()),
+            ("class_upload", stats.upload.into()),
+            ("class_num_served", stats.num_served.into()),
+            (
+                "class_mean_completion_time",
+                (stats.serve_time / stats.num_served.max(1) as f64).into(),
+            ),
+        ])?
+    }
+    latency_output.finish()?;
+    class_output.finish()?;
+    Ok(())
+}
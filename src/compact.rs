@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Class, NodeId, Target};
+
+// array-backed counterpart of `TrieOverlay`, bulk built from a node list
+// every subtrie is a range of `node_ids` (sorted by key), and every range of more than one node
+// has a fork recording the key bit it splits at. forks are stored in preorder, so the zero branch
+// of a fork is the next fork in the arena and only the one branch needs an index
+// compression is implicit: a fork splits at the highest differing bit of its range
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompactTrieOverlay {
+    node_ids: Vec<NodeId>,
+    forks: Vec<Fork>,
+    class: Class,
+}
+
+// also the forks of every class of `UnifiedOverlay`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct Fork {
+    pub(crate) level: u32,
+    pub(crate) mid: u32, // first index of the one branch in `node_ids`
+    pub(crate) one: u32, // index of the one branch in `forks`
+}
+
+// the range has no differing bit i.e. all keys are equal
+pub(crate) const BUCKET_LEVEL: u32 = u32::MAX;
+
+// append the forks of `node_ids[lo..hi]`, which is sorted by `key`, to `forks` in preorder
+pub(crate) fn build<T: Copy>(
+    node_ids: &[T],
+    lo: usize,
+    hi: usize,
+    key: &impl Fn(T) -> NodeId,
+    forks: &mut Vec<Fork>,
+) {
+    if hi - lo <= 1 {
+        return;
+    }
+    let diff = key(node_ids[lo]) ^ key(node_ids[hi - 1]);
+    if diff == 0 {
+        forks.push(Fork {
+            level: BUCKET_LEVEL,
+            mid: hi as _,
+            one: 0,
+        });
+        return;
+    }
+    let level = NodeId::BITS - 1 - diff.leading_zeros();
+    let mid = lo + node_ids[lo..hi].partition_point(|&node_id| (key(node_id) >> level) & 1 == 0);
+    let index = forks.len();
+    forks.push(Fork {
+        level,
+        mid: mid as _,
+        one: 0,
+    });
+    build(node_ids, lo, mid, key, forks);
+    forks[index].one = forks.len() as _;
+    build(node_ids, mid, hi, key, forks)
+}
+
+impl CompactTrieOverlay {
+    pub fn from_node_ids(node_ids: Vec<NodeId>) -> Self {
+        Self::from_classified_node_ids(node_ids, 0)
+    }
+
+    pub(crate) fn from_classified_node_ids(mut node_ids: Vec<NodeId>, class: Class) -> Self {
+        // shifting out the ignored highest bits preserves the order of classified distance. ids
+        // break ties, so that equal keys end up in the same bucket in id order
+        node_ids.sort_unstable_by_key(|&id| (id << class, id));
+        let mut forks = Vec::with_capacity(node_ids.len().saturating_sub(1));
+        build(&node_ids, 0, node_ids.len(), &|id| id << class, &mut forks);
+        Self {
+            node_ids,
+            forks,
+            class,
+        }
+    }
+
+    fn key(&self, id: NodeId) -> NodeId {
+        id << self.class
+    }
+
+    pub fn len(&self) -> usize {
+        self.node_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node_ids.is_empty()
+    }
+
+    pub fn node_ids(&self) -> &[NodeId] {
+        &self.node_ids
+    }
+
+    pub fn contains(&self, node_id: NodeId) -> bool {
+        self.node_ids
+            .binary_search_by_key(&(self.key(node_id), node_id), |&id| (self.key(id), id))
+            .is_ok()
+    }
+
+    pub fn find(&self, target: Target, count: usize) -> Vec<NodeId> {
+        let mut node_ids = Vec::with_capacity(count.min(self.len()));
+        if count > 0 && !self.is_empty() {
+            self.find_range(self.key(target), count, 0, self.len(), 0, &mut node_ids)
+        }
+        node_ids
+    }
+
+    // `class` is fixed at construction, so the classified search is the same as the plain one
+    pub(crate) fn find_classified(&self, target: Target, count: usize) -> Vec<NodeId> {
+        self.find(target, count)
+    }
+
+    fn find_range(
+        &self,
+        target_key: NodeId,
+        count: usize,
+        lo: usize,
+        hi: usize,
+        fork_index: usize,
+        node_ids: &mut Vec<NodeId>,
+    ) {
+        if hi - lo == 1 {
+            node_ids.push(self.node_ids[lo]);
+            return;
+        }
+        let fork = self.forks[fork_index];
+        if fork.level == BUCKET_LEVEL {
+            let take = count - node_ids.len();
+            node_ids.extend(self.node_ids[lo..hi].iter().take(take));
+            return;
+        }
+        let mid = fork.mid as usize;
+        let zero = (lo, mid, fork_index + 1);
+        let one = (mid, hi, fork.one as usize);
+        let (primary, secondary) = if (target_key >> fork.level) & 1 == 0 {
+            (zero, one)
+        } else {
+            (one, zero)
+        };
+        self.find_range(target_key, count, primary.0, primary.1, primary.2, node_ids);
+        if node_ids.len() < count {
+            self.find_range(
+                target_key,
+                count,
+                secondary.0,
+                secondary.1,
+                secondary.2,
+                node_ids,
+            )
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use std::fmt;
+
+use crate::NodeId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    DuplicateNode(NodeId),
+    // the overlay has fewer nodes than `find` asked for
+    NotEnoughNode { count: usize, found: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateNode(node_id) => write!(f, "node {node_id:016x} is already inserted"),
+            Self::NotEnoughNode { count, found } => {
+                write!(f, "find {count} nodes but only {found} are available")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+
+// summary statistics of how evenly load is spread over nodes, relative to the ideal share where
+// every node serves in proportion to its capacity
+// all metrics are computed over the normalized load of each node, i.e. its hit count divided by
+// its ideal hit count, so 1.0 everywhere is perfectly fair
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Fairness {
+    pub gini: f64,
+    pub jain: f64,
+    pub max_mean_ratio: f64,
+    pub coefficient_of_variation: f64,
+    // earth mover's distance between the capacity-weighted distribution of normalized load and
+    // the point mass at 1.0, which is the same as the L1 distance between the actual and the ideal
+    // share vectors. ranges from 0 (ideal) to 2
+    pub earth_mover_distance: f64,
+}
+
+impl Fairness {
+    // `loads` is (hit count, capacity) of every node
+    pub fn new(loads: &[(u64, u64)]) -> Self {
+        Self::from_shares(
+            &loads
+                .iter()
+                .map(|&(hit, capacity)| (hit as f64, capacity as f64))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    // the same for loads that are not counts, e.g. exact shares of the key space
+    pub fn from_shares(loads: &[(f64, f64)]) -> Self {
+        let total_hit = loads.iter().map(|&(hit, _)| hit).sum::<f64>();
+        let total_capacity = loads.iter().map(|&(_, capacity)| capacity).sum::<f64>();
+        let mut normalized = loads
+            .iter()
+            .map(|&(hit, capacity)| {
+                let ideal = total_hit * capacity / total_capacity;
+                if ideal == 0. { 0. } else { hit / ideal }
+            })
+            .collect::<Vec<_>>();
+
+        let n = normalized.len() as f64;
+        let sum = normalized.iter().sum::<f64>();
+        let mean = sum / n;
+        let square_sum = normalized.iter().map(|x| x * x).sum::<f64>();
+        let variance = square_sum / n - mean * mean;
+        let max = normalized.iter().copied().fold(0., f64::max);
+
+        normalized.sort_unstable_by(f64::total_cmp);
+        // G = sum_i (2i - n - 1) x_i / (n sum_i x_i), with x sorted ascending and i from 1
+        let gini = normalized
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (2. * (i + 1) as f64 - n - 1.) * x)
+            .sum::<f64>()
+            / (n * sum);
+
+        let earth_mover_distance = loads
+            .iter()
+            .map(|&(hit, capacity)| (hit / total_hit - capacity / total_capacity).abs())
+            .sum();
+
+        Self {
+            gini,
+            jain: sum * sum / (n * square_sum),
+            max_mean_ratio: max / mean,
+            coefficient_of_variation: variance.max(0.).sqrt() / mean,
+            earth_mover_distance,
+        }
+    }
+
+    pub fn fields(&self) -> [(&'static str, f64); 5] {
+        [
+            ("gini", self.gini),
+            ("jain", self.jain),
+            ("max_mean_ratio", self.max_mean_ratio),
+            ("coefficient_of_variation", self.coefficient_of_variation),
+            ("earth_mover_distance", self.earth_mover_distance),
+        ]
+    }
+}
@@ -0,0 +1,61 @@
+use rand::Rng;
+
+use crate::{Class, NodeId, Target, classified};
+
+// ids derived from key material and object names or content, instead of drawn at random
+// every kind of input is hashed under its own context, so that e.g. an object cannot be named
+// after a node's public key to land on the node's id. the id is the first bytes of the hash, so
+// the highest bits of the id are the first bits of the hash
+
+pub type PublicKey = [u8; 32];
+
+const NODE_CONTEXT: &str = "storage-simulation node id";
+const NAME_CONTEXT: &str = "storage-simulation object name";
+const CONTENT_CONTEXT: &str = "storage-simulation object content";
+
+fn truncate(hash: blake3::Hash) -> u64 {
+    u64::from_be_bytes(hash.as_bytes()[..8].try_into().unwrap())
+}
+
+fn derive(context: &str, input: &[u8]) -> u64 {
+    truncate(
+        blake3::Hasher::new_derive_key(context)
+            .update(input)
+            .finalize(),
+    )
+}
+
+// stands in for the public half of a freshly generated key pair, which is as good as random bytes
+// for the id it derives
+pub fn random_key(rng: &mut impl Rng) -> PublicKey {
+    rng.random()
+}
+
+pub fn node_id(public_key: &PublicKey) -> NodeId {
+    derive(NODE_CONTEXT, public_key)
+}
+
+pub fn name_target(name: &str) -> Target {
+    derive(NAME_CONTEXT, name.as_bytes())
+}
+
+pub fn content_target(content: &[u8]) -> Target {
+    derive(CONTENT_CONTEXT, content)
+}
+
+// keyed grinding: generate `num_attempt` key pairs and keep the one whose id is closest to
+// `target` as seen by a node of `class`. unlike `adversary::Position`, every attempt costs a hash
+pub fn grind(
+    target: Target,
+    class: Class,
+    num_attempt: u32,
+    rng: &mut impl Rng,
+) -> (PublicKey, NodeId) {
+    (0..num_attempt.max(1))
+        .map(|_| {
+            let public_key = random_key(rng);
+            (public_key, node_id(&public_key))
+        })
+        .min_by_key(|&(_, id)| classified::distance(id, target, class))
+        .unwrap()
+}
@@ -0,0 +1,253 @@
+use rand::Rng;
+use rustc_hash::FxHashMap;
+
+use crate::{Distance, NodeId, Overlay, Target, classified};
+
+// exact partition of the key space into ranges of targets that `find` the same set of nodes
+//
+// a range is the targets with the bits in `mask` equal to `bits`. within it, a node's classified
+// distance to a target is bounded by the distance with the free bits matching and with them
+// differing, and a node can only be one of the `count` closest if its least distance is not beyond
+// the `count`th least greatest distance. once exactly `count` nodes remain, every target in the
+// range finds them. the candidates only shrink while a range is split, so each range starts from
+// the candidates of its parent
+//
+// a range is split on the highest free bit that some candidate does not ignore. for class 0 this
+// is the highest free bit, i.e. ranges of common prefix, but the nodes of high classes only tell
+// the low bits of the targets apart, and splitting on the high bits first would never separate them
+//
+// the number of ranges grows with how far apart the classes are, since the nodes of each class
+// cut the targets along different bits. it is fine for the few classes of the experiments, but
+// not for a handful of nodes spread over all 64 classes
+//
+// the vanilla overlay is the same as every node in class 0
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range {
+    pub mask: Target,
+    pub bits: Target,
+    // in the order of id, not of distance, which may differ between the targets of the range
+    pub node_ids: Vec<NodeId>,
+}
+
+impl Range {
+    // fraction of the key space
+    pub fn width(&self) -> f64 {
+        width(self.mask)
+    }
+
+    pub fn contains(&self, target: Target) -> bool {
+        target & self.mask == self.bits
+    }
+}
+
+fn width(mask: Target) -> f64 {
+    (-(mask.count_ones() as f64)).exp2()
+}
+
+// the bit to split the range on, if any bit of the targets still matters
+fn split_bit(candidates: &[classified::NodeId], mask: Target) -> Option<Target> {
+    let min_class = candidates.iter().map(|&(_, class)| class).min()?;
+    let free = !mask & !0 >> min_class;
+    (free != 0).then(|| 1 << (NodeId::BITS - 1 - free.leading_zeros()))
+}
+
+// candidates are in the order of id, and stay so
+fn narrow(
+    candidates: &[classified::NodeId],
+    mask: Target,
+    bits: Target,
+    count: usize,
+) -> Vec<classified::NodeId> {
+    if candidates.len() <= count {
+        return candidates.to_vec();
+    }
+    let bound = |(id, class): classified::NodeId| -> (Distance, Distance) {
+        let class_mask = !0 >> class;
+        let least = (id ^ bits) & class_mask & mask;
+        (least, least | (!mask & class_mask))
+    };
+    let mut greatest = candidates
+        .iter()
+        .map(|&node_id| (bound(node_id).1, node_id.0))
+        .collect::<Vec<_>>();
+    let threshold = *greatest.select_nth_unstable(count - 1).1;
+    candidates
+        .iter()
+        .filter(|&&node_id| (bound(node_id).0, node_id.0) <= threshold)
+        .copied()
+        .collect()
+}
+
+fn sorted(node_ids: &[classified::NodeId]) -> Vec<classified::NodeId> {
+    let mut node_ids = node_ids.to_vec();
+    node_ids.sort_unstable();
+    node_ids
+}
+
+// there may be millions of ranges, which are passed to `f` one by one instead of collected
+pub fn partition(node_ids: &[classified::NodeId], count: usize, mut f: impl FnMut(Range)) {
+    if count > 0 {
+        partition_range(sorted(node_ids), 0, 0, count, &mut f)
+    }
+}
+
+fn partition_range(
+    candidates: Vec<classified::NodeId>,
+    mask: Target,
+    bits: Target,
+    count: usize,
+    f: &mut impl FnMut(Range),
+) {
+    let candidates = narrow(&candidates, mask, bits, count);
+    // every candidate has an exact distance once no bit it looks at is free, and distinct ids
+    // break the ties
+    let split_bit = split_bit(&candidates, mask).filter(|_| candidates.len() > count);
+    let Some(split_bit) = split_bit else {
+        assert!(candidates.len() <= count, "duplicated node id");
+        f(Range {
+            mask,
+            bits,
+            node_ids: candidates.into_iter().map(|(id, _)| id).collect(),
+        });
+        return;
+    };
+    partition_range(candidates.clone(), mask | split_bit, bits, count, f);
+    partition_range(candidates, mask | split_bit, bits | split_bit, count, f)
+}
+
+// fraction of the key space whose `find` of `count` nodes includes each node, i.e. its expected
+// share of the finds without sampling. the fractions sum to `count` with at least `count` nodes
+pub fn ownership(node_ids: &[classified::NodeId], count: usize) -> FxHashMap<NodeId, f64> {
+    let mut ownership = node_ids
+        .iter()
+        .map(|&(id, _)| (id, 0.))
+        .collect::<FxHashMap<_, _>>();
+    partition(node_ids, count, |range| {
+        for node_id in &range.node_ids {
+            *ownership.get_mut(node_id).unwrap() += range.width()
+        }
+    });
+    ownership
+}
+
+// the same estimated over random targets, for populations whose classes are too far apart to
+// partition the key space exactly
+pub fn sampled_ownership(
+    overlay: &Overlay,
+    node_ids: &[NodeId],
+    count: usize,
+    num_target: usize,
+    rng: &mut impl Rng,
+) -> FxHashMap<NodeId, f64> {
+    let mut ownership = node_ids
+        .iter()
+        .map(|&id| (id, 0.))
+        .collect::<FxHashMap<_, _>>();
+    for _ in 0..num_target {
+        for node_id in overlay.find(rng.random(), count) {
+            *ownership.get_mut(&node_id).unwrap() += 1. / num_target as f64
+        }
+    }
+    ownership
+}
+
+// what a membership change does to the results of `find`, as fractions of the key space
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Change {
+    // targets that find a different set of nodes
+    pub changed: f64,
+    // replicas that are stored on a node that did not store them before, out of `count` per target
+    pub moved: f64,
+}
+
+// exact change from the `before` population to the `after` one
+pub fn change(before: &[classified::NodeId], after: &[classified::NodeId], count: usize) -> Change {
+    let mut change = Change::default();
+    if count > 0 {
+        change_range(sorted(before), sorted(after), 0, 0, count, &mut change)
+    }
+    change
+}
+
+fn change_range(
+    before: Vec<classified::NodeId>,
+    after: Vec<classified::NodeId>,
+    mask: Target,
+    bits: Target,
+    count: usize,
+    change: &mut Change,
+) {
+    // the same candidates find the same nodes everywhere in the range, which is most of the key
+    // space for a change of a few nodes
+    if before == after {
+        return;
+    }
+    let before = narrow(&before, mask, bits, count);
+    let after = narrow(&after, mask, bits, count);
+    if before.len() <= count && after.len() <= count {
+        // both in the order of id. a node that only changed class stays where it was
+        let num_moved = after
+            .iter()
+            .filter(|&&(id, _)| before.binary_search_by_key(&id, |&(id, _)| id).is_err())
+            .count();
+        let num_removed = before
+            .iter()
+            .filter(|&&(id, _)| after.binary_search_by_key(&id, |&(id, _)| id).is_err())
+            .count();
+        if num_moved > 0 || num_removed > 0 {
+            change.changed += width(mask);
+            change.moved += width(mask) * num_moved as f64 / count as f64
+        }
+        return;
+    }
+    let candidates = if before.len() > count {
+        &before
+    } else {
+        &after
+    };
+    let split_bit = split_bit(candidates, mask).expect("duplicated node id");
+    change_range(
+        before.clone(),
+        after.clone(),
+        mask | split_bit,
+        bits,
+        count,
+        change,
+    );
+    change_range(
+        before,
+        after,
+        mask | split_bit,
+        bits | split_bit,
+        count,
+        change,
+    )
+}
+
+// the same estimated over random targets, for overlays that are not given as node lists
+pub fn sampled_change(
+    before: &Overlay,
+    after: &Overlay,
+    count: usize,
+    num_target: usize,
+    rng: &mut impl Rng,
+) -> Change {
+    let mut change = Change::default();
+    for _ in 0..num_target {
+        let target = rng.random();
+        let before = before.find(target, count);
+        let after = after.find(target, count);
+        let num_moved = after
+            .iter()
+            .filter(|node_id| !before.contains(node_id))
+            .count();
+        if num_moved > 0 || before.len() != after.len() {
+            change.changed += 1.;
+            change.moved += num_moved as f64 / count as f64
+        }
+    }
+    change.changed /= num_target as f64;
+    change.moved /= num_target as f64;
+    change
+}
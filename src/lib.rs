@@ -1,5 +1,31 @@
 use std::cell::RefCell;
 
+use serde::{Deserialize, Serialize};
+
+pub mod adversary;
+mod compact;
+mod error;
+pub mod fairness;
+pub mod identity;
+pub mod keyspace;
+pub mod model;
+pub mod network;
+pub mod output;
+pub mod placement;
+pub mod progress;
+pub mod read;
+pub mod resource;
+pub mod sim;
+pub mod snapshot;
+mod sorted;
+pub mod stats;
+mod unified;
+
+pub use compact::CompactTrieOverlay;
+pub use error::Error;
+pub use sorted::SortedOverlay;
+pub use unified::UnifiedOverlay;
+
 #[global_allocator]
 static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
@@ -31,12 +57,14 @@ pub mod classified {
         (node_id ^ target) & (!0 >> class)
     }
 
+    // distinct nodes tie when they differ only in the bits their classes ignore, and the smaller
+    // id comes first then
     pub fn find(
         node_ids: &mut [NodeId],
         target: super::Target,
         count: usize,
     ) -> Vec<super::NodeId> {
-        node_ids.sort_unstable_by_key(|&(id, class)| distance(id, target, class));
+        node_ids.sort_unstable_by_key(|&(id, class)| (distance(id, target, class), id));
         node_ids
             .iter()
             .take(count)
@@ -44,6 +72,11 @@ pub mod classified {
             .collect()
     }
 
+    // the class a node of `capacity` is assigned to, so that every class step doubles capacity
+    pub fn capacity_class(capacity: u64) -> super::Class {
+        capacity.checked_ilog2().unwrap_or(0) as _
+    }
+
     pub fn subnet_index(id: super::NodeId, class: super::Class) -> usize {
         // take the next (up to) SUBNET_BITS bits from the `class`th highest bit
         // when class is large, just make sure to include every bit starting with the `class`th
@@ -52,21 +85,82 @@ pub mod classified {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Overlay {
     Vanilla(BinOverlay),
     Classified(Classified),
 }
 
 impl Overlay {
+    // `class` is ignored by vanilla overlay
+    pub fn insert_node(&mut self, node_id: NodeId, class: Class) {
+        match self {
+            Self::Vanilla(overlay) => overlay.insert_node(node_id),
+            Self::Classified(overlay) => overlay.insert_node(node_id, class),
+        }
+    }
+
+    pub fn try_insert_node(&mut self, node_id: NodeId, class: Class) -> Result<(), Error> {
+        match self {
+            Self::Vanilla(overlay) => overlay.try_insert_node(node_id),
+            Self::Classified(overlay) => overlay.try_insert_node(node_id, class),
+        }
+    }
+
+    pub fn contains(&self, node_id: NodeId) -> bool {
+        match self {
+            Self::Vanilla(overlay) => overlay.contains(node_id),
+            Self::Classified(overlay) => overlay.contains(node_id),
+        }
+    }
+
+    pub fn remove_node(&mut self, node_id: NodeId, class: Class) -> bool {
+        match self {
+            Self::Vanilla(overlay) => overlay.remove_node(node_id),
+            Self::Classified(overlay) => overlay.remove_node(node_id, class),
+        }
+    }
+
+    // false if the node is not in the overlay. vanilla overlay ranks every node the same whatever
+    // its class, so there is nothing to update
+    pub fn update_class(&mut self, node_id: NodeId, class: Class) -> bool {
+        match self {
+            Self::Vanilla(overlay) => overlay.contains(node_id),
+            Self::Classified(overlay) => overlay.update_class(node_id, class).is_some(),
+        }
+    }
+
+    pub fn optimize(&mut self) {
+        if let Self::Classified(overlay) = self {
+            overlay.optimize()
+        }
+    }
+
+    // every overlay returns the same nodes in the same order as `find` and `classified::find`, i.e.
+    // sorted by distance to `target` with ties broken by node id, so the first node can be told
+    // apart from the rest e.g. as the primary replica
+    // when there are fewer than `count` nodes, all of them are returned
     pub fn find(&self, target: Target, count: usize) -> Vec<NodeId> {
         match self {
             Self::Vanilla(overlay) => overlay.find(target, count),
             Self::Classified(overlay) => overlay.find(target, count),
         }
     }
+
+    // same as `find`, but fewer than `count` nodes is an error
+    pub fn try_find(&self, target: Target, count: usize) -> Result<Vec<NodeId>, Error> {
+        let node_ids = self.find(target, count);
+        if node_ids.len() < count {
+            return Err(Error::NotEnoughNode {
+                count,
+                found: node_ids.len(),
+            });
+        }
+        Ok(node_ids)
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BinOverlay {
     subnets: Vec<Vec<NodeId>>,
 }
@@ -94,6 +188,35 @@ impl BinOverlay {
         self.subnets[classified::subnet_index(target, class)].push(target)
     }
 
+    pub fn try_insert_node(&mut self, node_id: NodeId) -> Result<(), Error> {
+        if self.contains(node_id) {
+            return Err(Error::DuplicateNode(node_id));
+        }
+        self.insert_node(node_id);
+        Ok(())
+    }
+
+    pub fn contains(&self, node_id: NodeId) -> bool {
+        self.contains_classified(node_id, 0)
+    }
+
+    fn contains_classified(&self, node_id: NodeId, class: Class) -> bool {
+        self.subnets[classified::subnet_index(node_id, class)].contains(&node_id)
+    }
+
+    pub fn remove_node(&mut self, node_id: NodeId) -> bool {
+        self.remove_classified_node(node_id, 0)
+    }
+
+    fn remove_classified_node(&mut self, node_id: NodeId, class: Class) -> bool {
+        let subnet = &mut self.subnets[classified::subnet_index(node_id, class)];
+        let Some(index) = subnet.iter().position(|&id| id == node_id) else {
+            return false;
+        };
+        subnet.swap_remove(index);
+        true
+    }
+
     pub fn find(&self, target: Target, count: usize) -> Vec<NodeId> {
         self.find_classified(target, count, 0)
     }
@@ -103,12 +226,9 @@ impl BinOverlay {
         let mut node_ids = Vec::new();
         for diff in 0..1 << SUBNET_BITS {
             let mut subnet = self.subnets[target_subnet_index ^ diff].clone();
-            if subnet.len() <= count - node_ids.len() {
-                node_ids.extend(subnet)
-            } else {
-                subnet.sort_unstable_by_key(|&id| classified::distance(id, target, class));
-                node_ids.extend(subnet.into_iter().take(count - node_ids.len()))
-            }
+            // subnets come in the order of distance, but nodes inside are not
+            subnet.sort_unstable_by_key(|&id| (classified::distance(id, target, class), id));
+            node_ids.extend(subnet.into_iter().take(count - node_ids.len()));
             if node_ids.len() == count {
                 break;
             }
@@ -117,25 +237,35 @@ impl BinOverlay {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrieOverlay {
     data: TrieData,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 enum TrieData {
     Empty,
     Node(NodeId),
     Fork(Box<SubTries>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct SubTries {
     zero: TrieOverlay,
     one: TrieOverlay,
     skip: u32,
 }
 
+impl SubTries {
+    // a fork always has some node below, even after removing turns one side empty
+    fn first_node_id(&self) -> NodeId {
+        self.zero
+            .first_node_id()
+            .or(self.one.first_node_id())
+            .unwrap()
+    }
+}
+
 impl Default for TrieOverlay {
     fn default() -> Self {
         Self::new()
@@ -161,7 +291,7 @@ impl TrieOverlay {
         (node_id >> level) & 1 == 0
     }
 
-    fn insert_node_level(&mut self, node_id: NodeId, level: u32) {
+    fn insert_node_level(&mut self, node_id: NodeId, mut level: u32) {
         match &mut self.data {
             TrieData::Empty => self.data = TrieData::Node(node_id),
             TrieData::Node(other_node_id) => {
@@ -174,7 +304,8 @@ impl TrieOverlay {
                     } else {
                         &mut trie1
                     }
-                    .insert_node_level(node_id, level - 1)
+                    // a fork at level 0 has nothing but nodes below, which do not need a level
+                    .insert_node_level(node_id, level.saturating_sub(1))
                 }
                 self.data = TrieData::Fork(
                     SubTries {
@@ -185,12 +316,120 @@ impl TrieOverlay {
                     .into(),
                 )
             }
-            TrieData::Fork(fork) => if Self::level_bit(node_id, level) {
-                &mut fork.zero
-            } else {
-                &mut fork.one
+            TrieData::Fork(fork) => {
+                if fork.skip > 0 {
+                    // the skipped levels are the same for every node below, so compare them with
+                    // any of those nodes, and branch off above the fork at the highest level the
+                    // new node differs
+                    let fork_level = level - fork.skip;
+                    let diff = ((node_id ^ fork.first_node_id()) >> (fork_level + 1))
+                        & ((1 << fork.skip) - 1);
+                    if diff != 0 {
+                        let diff_level = fork_level + NodeId::BITS - diff.leading_zeros();
+                        fork.skip = diff_level - 1 - fork_level;
+                        let trie = Self {
+                            data: std::mem::replace(&mut self.data, TrieData::Empty),
+                        };
+                        let node = Self {
+                            data: TrieData::Node(node_id),
+                        };
+                        let (zero, one) = if Self::level_bit(node_id, diff_level) {
+                            (node, trie)
+                        } else {
+                            (trie, node)
+                        };
+                        self.data = TrieData::Fork(
+                            SubTries {
+                                zero,
+                                one,
+                                skip: level - diff_level,
+                            }
+                            .into(),
+                        );
+                        return;
+                    }
+                    level = fork_level
+                }
+                if Self::level_bit(node_id, level) {
+                    &mut fork.zero
+                } else {
+                    &mut fork.one
+                }
+                .insert_node_level(node_id, level.saturating_sub(1))
+            }
+        }
+    }
+
+    pub fn try_insert_node(&mut self, node_id: NodeId) -> Result<(), Error> {
+        if self.contains(node_id) {
+            return Err(Error::DuplicateNode(node_id));
+        }
+        self.insert_node(node_id);
+        Ok(())
+    }
+
+    pub fn contains(&self, node_id: NodeId) -> bool {
+        self.contains_level(node_id, NodeId::BITS - 1)
+    }
+
+    fn contains_level(&self, node_id: NodeId, mut level: u32) -> bool {
+        match &self.data {
+            TrieData::Empty => false,
+            TrieData::Node(other_node_id) => *other_node_id == node_id,
+            TrieData::Fork(fork) => {
+                level -= fork.skip;
+                if Self::level_bit(node_id, level) {
+                    &fork.zero
+                } else {
+                    &fork.one
+                }
+                .contains_level(node_id, level.saturating_sub(1))
+            }
+        }
+    }
+
+    fn first_node_id(&self) -> Option<NodeId> {
+        match &self.data {
+            TrieData::Empty => None,
+            TrieData::Node(node_id) => Some(*node_id),
+            TrieData::Fork(fork) => fork.first_node_id().into(),
+        }
+    }
+
+    pub fn remove_node(&mut self, node_id: NodeId) -> bool {
+        self.remove_node_level(node_id, NodeId::BITS - 1)
+    }
+
+    fn remove_node_level(&mut self, node_id: NodeId, mut level: u32) -> bool {
+        match &mut self.data {
+            TrieData::Empty => false,
+            TrieData::Node(other_node_id) => {
+                if *other_node_id != node_id {
+                    return false;
+                }
+                self.data = TrieData::Empty;
+                true
+            }
+            TrieData::Fork(fork) => {
+                level -= fork.skip;
+                let sub_trie = if Self::level_bit(node_id, level) {
+                    &mut fork.zero
+                } else {
+                    &mut fork.one
+                };
+                if !sub_trie.remove_node_level(node_id, level.saturating_sub(1)) {
+                    return false;
+                }
+                // a fork with a single node left turns into that node, which is valid at any
+                // level. a fork with a single fork left is kept as it is until next `compress`
+                use TrieData::*;
+                match (&fork.zero.data, &fork.one.data) {
+                    (Empty, Empty) => self.data = Empty,
+                    (Empty, &Node(node_id)) | (&Node(node_id), Empty) => self.data = Node(node_id),
+                    _ => {}
+                }
+                true
             }
-            .insert_node_level(node_id, level - 1),
         }
     }
 
@@ -206,8 +445,10 @@ impl TrieOverlay {
             (Empty, _) | (_, Empty) => unreachable!(),
             _ => return,
         };
+        // this fork may have been compressed before and got a single child by removing since
+        let skip = fork.skip;
         *fork = nested_fork;
-        fork.skip += 1
+        fork.skip += skip + 1
     }
 
     #[cfg(test)]
@@ -224,6 +465,9 @@ impl TrieOverlay {
     }
 
     fn find_classified(&self, target: Target, count: usize, class: Class) -> Vec<NodeId> {
+        if count == 0 {
+            return vec![];
+        }
         self.find_level(target, count, NodeId::BITS - 1 - class as u32)
     }
 
@@ -245,12 +489,12 @@ impl TrieOverlay {
                     // let ts = [&fork.one, &fork.zero];
                     // (ts[b], ts[1 - b])
                 };
-                let mut node_ids = primary_trie.find_level(target, count, level - 1);
+                let mut node_ids = primary_trie.find_level(target, count, level.saturating_sub(1));
                 if node_ids.len() < count {
                     node_ids.extend(secondary_trie.find_level(
                         target,
                         count - node_ids.len(),
-                        level - 1,
+                        level.saturating_sub(1),
                     ))
                 }
                 node_ids
@@ -259,16 +503,37 @@ impl TrieOverlay {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Classified {
     classes: Vec<ClassOverlay>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ClassOverlay {
     Naive(RefCell<Vec<NodeId>>), // interior mutability for sorting inside `find`
-    Trie(TrieOverlay),
-    Bin(BinOverlay),
+    Compact(CompactTrieOverlay),
+}
+
+impl ClassOverlay {
+    fn contains(&self, node_id: NodeId) -> bool {
+        match self {
+            Self::Naive(node_ids) => node_ids.borrow().contains(&node_id),
+            Self::Compact(overlay) => overlay.contains(node_id),
+        }
+    }
+
+    // turn back into the naive overlay to be mutated, until optimized again
+    fn naive(&mut self) -> &mut Vec<NodeId> {
+        let node_ids = match self {
+            Self::Naive(node_ids) => return node_ids.get_mut(),
+            Self::Compact(overlay) => overlay.node_ids().to_vec(),
+        };
+        *self = Self::Naive(node_ids.into());
+        let Self::Naive(node_ids) = self else {
+            unreachable!()
+        };
+        node_ids.get_mut()
+    }
 }
 
 impl Default for Classified {
@@ -283,37 +548,69 @@ impl Classified {
         }
     }
 
+    // an optimized class is turned back into the naive overlay, in time linear in its size, and
+    // is only rebuilt on the next `optimize`. so is one a node is removed from
     pub fn insert_node(&mut self, node_id: NodeId, class: Class) {
         if class as usize >= self.classes.len() {
             self.classes
                 .resize_with((class + 1) as _, || ClassOverlay::Naive(Default::default()))
         }
-        let ClassOverlay::Naive(node_ids) = &mut self.classes[class as usize] else {
-            unimplemented!()
+        self.classes[class as usize].naive().push(node_id)
+    }
+
+    // a node id is unique across classes
+    pub fn try_insert_node(&mut self, node_id: NodeId, class: Class) -> Result<(), Error> {
+        if self.contains(node_id) {
+            return Err(Error::DuplicateNode(node_id));
+        }
+        self.insert_node(node_id, class);
+        Ok(())
+    }
+
+    pub fn contains(&self, node_id: NodeId) -> bool {
+        self.classes
+            .iter()
+            .any(|class_overlay| class_overlay.contains(node_id))
+    }
+
+    pub fn remove_node(&mut self, node_id: NodeId, class: Class) -> bool {
+        let Some(class_overlay) = self.classes.get_mut(class as usize) else {
+            return false;
+        };
+        let node_ids = class_overlay.naive();
+        let Some(index) = node_ids.iter().position(|&id| id == node_id) else {
+            return false;
         };
-        node_ids.borrow_mut().push(node_id)
+        node_ids.swap_remove(index);
+        true
     }
 
+    // move a node to another class in place, e.g. after its capacity changed, and return the
+    // class it was in. as with insertion, the classes involved are not optimized until `optimize`
+    pub fn update_class(&mut self, node_id: NodeId, class: Class) -> Option<Class> {
+        let old_class = (0..self.classes.len())
+            .find(|&old_class| self.classes[old_class].contains(node_id))?
+            as Class;
+        if old_class != class {
+            self.remove_node(node_id, old_class);
+            self.insert_node(node_id, class)
+        }
+        Some(old_class)
+    }
+
+    // classes that are already optimized are left as they are
     pub fn optimize(&mut self) {
         for (class, class_overlay) in self.classes.iter_mut().enumerate() {
             let ClassOverlay::Naive(node_ids) = &class_overlay else {
-                unimplemented!()
+                continue;
             };
             let replace_overlay = {
                 let node_ids = &*node_ids.borrow();
-                if node_ids.len() >= 512 {
-                    let mut overlay = BinOverlay::new();
-                    for &node_id in node_ids {
-                        overlay.insert_classified_node(node_id, class as _)
-                    }
-                    ClassOverlay::Bin(overlay)
-                } else if node_ids.len() >= 16 {
-                    let mut overlay = TrieOverlay::new();
-                    for &node_id in node_ids {
-                        overlay.insert_classified_node(node_id, class as _)
-                    }
-                    overlay.compress();
-                    ClassOverlay::Trie(overlay)
+                if node_ids.len() >= 16 {
+                    ClassOverlay::Compact(CompactTrieOverlay::from_classified_node_ids(
+                        node_ids.clone(),
+                        class as _,
+                    ))
                 } else {
                     continue;
                 }
@@ -331,13 +628,12 @@ impl Classified {
                 let class = class as _;
                 match class_overlay {
                     ClassOverlay::Naive(node_ids) => {
-                        node_ids
-                            .borrow_mut()
-                            .sort_unstable_by_key(|&id| classified::distance(id, target, class));
+                        node_ids.borrow_mut().sort_unstable_by_key(|&id| {
+                            (classified::distance(id, target, class), id)
+                        });
                         node_ids.borrow().iter().take(count).copied().collect()
                     }
-                    ClassOverlay::Trie(overlay) => overlay.find_classified(target, count, class),
-                    ClassOverlay::Bin(overlay) => overlay.find_classified(target, count, class),
+                    ClassOverlay::Compact(overlay) => overlay.find_classified(target, count),
                 }
                 .into_iter()
                 .map(move |id| (id, class))
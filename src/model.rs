@@ -0,0 +1,86 @@
+// closed form of the load the classified distance puts on every class, to check the simulation
+// against
+//
+// with the id space scaled to [0, 1), a node of class `c` has its distance to a uniformly random
+// target uniform on [0, 2^-c), since the `c` highest bits are ignored. a node at distance `x` is
+// found if fewer than `count` of the other nodes are closer, and a node of class `j` is closer
+// with probability min(1, x 2^j) independently of each other. so the number of closer nodes is a
+// sum of one binomial per class, and the expected hit rate of the node is the probability that
+// this sum is less than `count`, averaged over `x`
+
+// expected number of times a single node of each class is in the result of one `find` of `count`
+// nodes, for a population of `num_nodes[class]` nodes per class. the vanilla overlay is the same
+// as putting every node into class 0. a `find` of no node hits nothing
+pub fn class_hit_rate(num_nodes: &[usize], count: usize) -> Vec<f64> {
+    (0..num_nodes.len())
+        .map(|class| {
+            if num_nodes[class] == 0 || count == 0 {
+                return 0.;
+            }
+            hit_rate(num_nodes, class, count)
+        })
+        .collect()
+}
+
+fn hit_rate(num_nodes: &[usize], class: usize, count: usize) -> f64 {
+    let width = (-(class as f64)).exp2();
+    // the rate other nodes fall closer than `x` at, for small `x`. past a few times `count` over it
+    // the probability of a hit is negligible
+    let rate = num_nodes
+        .iter()
+        .enumerate()
+        .map(|(j, &n)| n as f64 * (j as f64).exp2())
+        .sum::<f64>();
+    let upper = width.min((count as f64 + 50. + 10. * (count as f64).sqrt()) / rate);
+    // the integrand has a kink where every node of some class becomes closer
+    let mut breaks = (0..num_nodes.len())
+        .map(|j| (-(j as f64)).exp2())
+        .filter(|&x| x < upper)
+        .collect::<Vec<_>>();
+    breaks.extend([0., upper]);
+    breaks.sort_by(f64::total_cmp);
+    breaks.dedup();
+    let integral = breaks
+        .windows(2)
+        .map(|window| simpson(|x| found(num_nodes, class, count, x), window[0], window[1]))
+        .sum::<f64>();
+    integral / width
+}
+
+// probability that fewer than `count` other nodes are closer than `x` to the target
+fn found(num_nodes: &[usize], class: usize, count: usize, x: f64) -> f64 {
+    // distribution of the number of closer nodes, truncated to the values below `count`
+    let mut distr = vec![0.; count];
+    distr[0] = 1.;
+    for (j, &n) in num_nodes.iter().enumerate() {
+        let n = n - (j == class) as usize;
+        let p = (x * (j as f64).exp2()).min(1.);
+        let pmf = binomial_pmf(n, p, count);
+        for s in (0..count).rev() {
+            distr[s] = (0..=s).map(|m| distr[s - m] * pmf[m]).sum()
+        }
+    }
+    distr.into_iter().sum()
+}
+
+// probability of 0 until `len` successes out of `n` trials
+fn binomial_pmf(n: usize, p: f64, len: usize) -> Vec<f64> {
+    let mut pmf = vec![0.; len];
+    let mut choose = 1.;
+    for (m, probability) in pmf.iter_mut().enumerate().take(n + 1) {
+        if m > 0 {
+            choose *= (n - m + 1) as f64 / m as f64
+        }
+        *probability = choose * p.powi(m as _) * (1. - p).powi((n - m) as _)
+    }
+    pmf
+}
+
+fn simpson(f: impl Fn(f64) -> f64, a: f64, b: f64) -> f64 {
+    const NUM_INTERVAL: usize = 1 << 10;
+    let h = (b - a) / NUM_INTERVAL as f64;
+    let sum = (1..NUM_INTERVAL)
+        .map(|i| f(a + i as f64 * h) * if i % 2 == 1 { 4. } else { 2. })
+        .sum::<f64>();
+    (f(a) + f(b) + sum) * h / 3.
+}
@@ -0,0 +1,185 @@
+use std::{fs::read_to_string, path::Path};
+
+use anyhow::{bail, ensure};
+use rand::Rng;
+use rand_distr::{Distribution, Exp, Normal};
+use rustc_hash::FxHashMap;
+
+use crate::NodeId;
+
+// all time in second, all size in byte
+
+// 2-D euclidean position plus a height for the access link, as in Vivaldi
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinate {
+    pub x: f64,
+    pub y: f64,
+    pub height: f64,
+}
+
+impl Coordinate {
+    pub fn rtt(&self, other: &Self) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y) + self.height + other.height
+    }
+
+    // a handful of continents with nodes scattered around them, spanning roughly 300ms of rtt
+    pub fn synthetic(rng: &mut impl Rng) -> Self {
+        const CONTINENTS: [(f64, f64); 5] = [
+            (0., 0.),
+            (0.080, 0.020),
+            (-0.070, 0.040),
+            (0.020, -0.110),
+            (0.150, -0.060),
+        ];
+        let (x, y) = CONTINENTS[rng.random_range(0..CONTINENTS.len())];
+        let spread = Normal::new(0., 0.015).unwrap();
+        Self {
+            x: x + spread.sample(rng),
+            y: y + spread.sample(rng),
+            height: Exp::new(1. / 0.005).unwrap().sample(rng),
+        }
+    }
+}
+
+// measured pairwise rtt between sites, e.g. the King or PlanetLab data sets
+#[derive(Debug, Clone)]
+pub struct LatencyMatrix {
+    rtt: Vec<f64>,
+    num_site: usize,
+}
+
+impl LatencyMatrix {
+    // whitespace-separated square matrix in microseconds, negative entries are missing
+    // measurements and are filled with the reverse direction if available, or the mean otherwise
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let rtt = read_to_string(path)?
+            .split_whitespace()
+            .map(|value| Ok(value.parse::<f64>()? / 1_000_000.))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let num_site = (rtt.len() as f64).sqrt() as usize;
+        ensure!(
+            num_site * num_site == rtt.len(),
+            "latency matrix is not square"
+        );
+        let mut matrix = Self { rtt, num_site };
+        let known = matrix.rtt.iter().filter(|&&rtt| rtt >= 0.);
+        let mean = known.clone().sum::<f64>() / known.count().max(1) as f64;
+        for a in 0..num_site {
+            for b in 0..num_site {
+                if matrix.rtt(a, b) < 0. {
+                    let reverse = matrix.rtt(b, a);
+                    matrix.rtt[a * num_site + b] = if reverse >= 0. { reverse } else { mean }
+                }
+            }
+        }
+        Ok(matrix)
+    }
+
+    pub fn num_site(&self) -> usize {
+        self.num_site
+    }
+
+    pub fn rtt(&self, a: usize, b: usize) -> f64 {
+        self.rtt[a * self.num_site + b]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Location {
+    Coordinate(Coordinate),
+    // row of the latency matrix
+    Site(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Link {
+    pub location: Location,
+    pub upload: f64,
+    pub download: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transfer {
+    pub client: NodeId,
+    pub server: NodeId,
+    pub size: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NetworkModel {
+    links: FxHashMap<NodeId, Link>,
+    matrix: Option<LatencyMatrix>,
+}
+
+impl NetworkModel {
+    // nodes are located by `Location::Site` if and only if there is a matrix
+    pub fn new(matrix: Option<LatencyMatrix>) -> Self {
+        Self {
+            links: Default::default(),
+            matrix,
+        }
+    }
+
+    pub fn matrix(&self) -> Option<&LatencyMatrix> {
+        self.matrix.as_ref()
+    }
+
+    // a node located by the other kind, or at a site out of the matrix, is an error, so that
+    // every pair of nodes has an rtt
+    pub fn insert_node(&mut self, node_id: NodeId, link: Link) -> anyhow::Result<()> {
+        match (&link.location, &self.matrix) {
+            (Location::Coordinate(_), None) => {}
+            (&Location::Site(site), Some(matrix)) => ensure!(
+                site < matrix.num_site(),
+                "site {site} out of {} sites",
+                matrix.num_site()
+            ),
+            (Location::Coordinate(_), Some(_)) => bail!("node located by coordinate with a matrix"),
+            (Location::Site(_), None) => bail!("node located by site without a matrix"),
+        }
+        self.links.insert(node_id, link);
+        Ok(())
+    }
+
+    pub fn link(&self, node_id: NodeId) -> &Link {
+        &self.links[&node_id]
+    }
+
+    pub fn rtt(&self, a: NodeId, b: NodeId) -> f64 {
+        match (&self.link(a).location, &self.link(b).location) {
+            (Location::Coordinate(a), Location::Coordinate(b)) => a.rtt(b),
+            (&Location::Site(a), &Location::Site(b)) => self.matrix.as_ref().unwrap().rtt(a, b),
+            _ => unreachable!("mixed locations are rejected on insertion"),
+        }
+    }
+
+    pub fn nearest(&self, client: NodeId, servers: &[NodeId]) -> Option<NodeId> {
+        servers
+            .iter()
+            .copied()
+            .min_by(|&a, &b| self.rtt(client, a).total_cmp(&self.rtt(client, b)))
+    }
+
+    // completion time of transfers that all start at the same time. every node splits its upload
+    // (resp. download) bandwidth evenly among the transfers it serves (resp. receives) and each
+    // transfer runs at the lower of its two shares. this is coarser than max-min fairness but
+    // enough to expose nodes that are asked to serve more than their bandwidth allows
+    pub fn complete(&self, transfers: &[Transfer]) -> Vec<f64> {
+        let mut num_upload = FxHashMap::<NodeId, usize>::default();
+        let mut num_download = FxHashMap::<NodeId, usize>::default();
+        for transfer in transfers {
+            *num_upload.entry(transfer.server).or_default() += 1;
+            *num_download.entry(transfer.client).or_default() += 1
+        }
+        transfers
+            .iter()
+            .map(|transfer| {
+                let upload =
+                    self.link(transfer.server).upload / num_upload[&transfer.server] as f64;
+                let download =
+                    self.link(transfer.client).download / num_download[&transfer.client] as f64;
+                self.rtt(transfer.client, transfer.server) + transfer.size / upload.min(download)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,445 @@
+use std::{
+    fs::{File, read_to_string},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::bail;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    U64(u64),
+    F64(f64),
+    Str(String),
+}
+
+impl Value {
+    // what a CSV field reads back as, which does not tell an integer written as a float apart
+    fn parse(s: &str) -> Self {
+        if let Ok(value) = s.parse() {
+            Self::U64(value)
+        } else if let Ok(value) = s.parse() {
+            Self::F64(value)
+        } else {
+            Self::Str(s.into())
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::U64(value) => Some(*value as _),
+            Self::F64(value) => Some(*value),
+            Self::Str(_) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Self::U64(value)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Self::U64(value as _)
+    }
+}
+
+impl From<u8> for Value {
+    fn from(value: u8) -> Self {
+        Self::U64(value as _)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Self::U64(value as _)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::F64(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Self::F64(value as _)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::Str(value.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::Str(value)
+    }
+}
+
+pub type Field = (&'static str, Value);
+
+// the columns shared by every record of a run: experiment parameters followed by what is needed
+// to reproduce the run. the commit is the one the binary is built from, see build.rs
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    fields: Vec<Field>,
+    seed: u64,
+}
+
+impl Manifest {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            fields: Default::default(),
+            seed,
+        }
+    }
+
+    pub fn param(mut self, name: &'static str, value: impl Into<Value>) -> Self {
+        self.fields.push((name, value.into()));
+        self
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = Field> + '_ {
+        self.fields.iter().cloned().chain([
+            ("seed", self.seed.into()),
+            ("git_commit", env!("GIT_COMMIT").into()),
+            ("crate_version", env!("CARGO_PKG_VERSION").into()),
+        ])
+    }
+}
+
+pub trait RecordWriter {
+    // every record of a writer is expected to have the same fields in the same order
+    fn write(&mut self, record: &[Field]) -> anyhow::Result<()>;
+
+    fn finish(self: Box<Self>) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    JsonLines,
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "csv" => Self::Csv,
+            "jsonl" => Self::JsonLines,
+            #[cfg(feature = "parquet")]
+            "parquet" => Self::Parquet,
+            _ => bail!("unknown output format {s}"),
+        })
+    }
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::JsonLines => "jsonl",
+            #[cfg(feature = "parquet")]
+            Self::Parquet => "parquet",
+        }
+    }
+
+    // `path` without extension. the extension is appended rather than set, so that parameters with
+    // a decimal point can be part of the file name
+    pub fn create(
+        &self,
+        path: impl AsRef<Path>,
+        manifest: &Manifest,
+    ) -> anyhow::Result<Box<dyn RecordWriter>> {
+        let mut path = path.as_ref().as_os_str().to_owned();
+        path.push(".");
+        path.push(self.extension());
+        let path = PathBuf::from(path);
+        let manifest = manifest.fields().collect();
+        Ok(match self {
+            Self::Csv => Box::new(CsvWriter {
+                file: BufWriter::new(File::create(path)?),
+                manifest,
+                header_written: false,
+            }),
+            Self::JsonLines => Box::new(JsonLinesWriter {
+                file: BufWriter::new(File::create(path)?),
+                manifest,
+            }),
+            #[cfg(feature = "parquet")]
+            Self::Parquet => Box::new(columnar::ParquetWriter::new(path, manifest)),
+        })
+    }
+}
+
+struct CsvWriter {
+    file: BufWriter<File>,
+    manifest: Vec<Field>,
+    header_written: bool,
+}
+
+impl CsvWriter {
+    fn write_line(&mut self, items: Vec<String>) -> anyhow::Result<()> {
+        writeln!(self.file, "{}", items.join(","))?;
+        Ok(())
+    }
+
+    fn escape(s: &str) -> String {
+        if s.contains([',', '"', '\n']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.into()
+        }
+    }
+}
+
+impl RecordWriter for CsvWriter {
+    fn write(&mut self, record: &[Field]) -> anyhow::Result<()> {
+        if !self.header_written {
+            let header = self
+                .manifest
+                .iter()
+                .chain(record)
+                .map(|(name, _)| name.to_string())
+                .collect::<Vec<_>>();
+            self.write_line(header)?;
+            self.header_written = true
+        }
+        let values = self
+            .manifest
+            .iter()
+            .chain(record)
+            .map(|(_, value)| match value {
+                Value::U64(value) => value.to_string(),
+                Value::F64(value) => value.to_string(),
+                Value::Str(value) => Self::escape(value),
+            })
+            .collect::<Vec<_>>();
+        self.write_line(values)
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+struct JsonLinesWriter {
+    file: BufWriter<File>,
+    manifest: Vec<Field>,
+}
+
+impl RecordWriter for JsonLinesWriter {
+    fn write(&mut self, record: &[Field]) -> anyhow::Result<()> {
+        let object = self
+            .manifest
+            .iter()
+            .chain(record)
+            .map(|(name, value)| {
+                let value = match value {
+                    Value::U64(value) => (*value).into(),
+                    Value::F64(value) => (*value).into(),
+                    Value::Str(value) => value.clone().into(),
+                };
+                (name.to_string(), value)
+            })
+            .collect::<serde_json::Map<_, _>>();
+        serde_json::to_writer(&mut self.file, &object)?;
+        writeln!(self.file)?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+pub type Record = Vec<(String, Value)>;
+
+// the records of a file written by a `RecordWriter`, in the format of its extension. parquet is
+// not read back
+pub fn read_records(path: impl AsRef<Path>) -> anyhow::Result<Vec<Record>> {
+    let path = path.as_ref();
+    let content = read_to_string(path)?;
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("csv") => {
+            let mut rows = parse_csv(&content)?.into_iter();
+            let Some(header) = rows.next() else {
+                return Ok(Vec::new());
+            };
+            rows.map(|row| {
+                if row.len() != header.len() {
+                    bail!("{} fields in a row of {}", row.len(), path.display())
+                }
+                Ok(header
+                    .iter()
+                    .cloned()
+                    .zip(row.iter().map(|value| Value::parse(value)))
+                    .collect())
+            })
+            .collect()
+        }
+        Some("jsonl") => content
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let object = serde_json::from_str::<serde_json::Map<_, _>>(line)?;
+                object
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let value = match value {
+                            serde_json::Value::Number(number) => match number.as_u64() {
+                                Some(value) => Value::U64(value),
+                                None => Value::F64(number.as_f64().unwrap()),
+                            },
+                            serde_json::Value::String(value) => Value::Str(value),
+                            // how serde_json writes a float that is not finite
+                            serde_json::Value::Null => Value::F64(f64::NAN),
+                            value => bail!("unexpected value {value} of field {name}"),
+                        };
+                        Ok((name, value))
+                    })
+                    .collect()
+            })
+            .collect(),
+        _ => bail!("unknown output format of {}", path.display()),
+    }
+}
+
+// the inverse of `CsvWriter::escape`, where quoted fields may span lines
+fn parse_csv(content: &str) -> anyhow::Result<Vec<Vec<String>>> {
+    let (mut rows, mut row, mut field) = (Vec::new(), Vec::new(), String::new());
+    let (mut quoted, mut chars) = (false, content.chars().peekable());
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"')
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row))
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        bail!("unterminated quoted field")
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row)
+    }
+    Ok(rows)
+}
+
+#[cfg(feature = "parquet")]
+mod columnar {
+    use std::{fs::File, path::PathBuf, sync::Arc};
+
+    use anyhow::bail;
+    use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray, UInt64Array};
+    use arrow_schema::{DataType, Field as ArrowField, Schema};
+    use parquet::arrow::ArrowWriter;
+
+    use super::{Field, RecordWriter, Value};
+
+    enum Column {
+        U64(Vec<u64>),
+        F64(Vec<f64>),
+        Str(Vec<String>),
+    }
+
+    // parquet is columnar, so records are buffered and written as a single batch on finish
+    pub struct ParquetWriter {
+        path: PathBuf,
+        manifest: Vec<Field>,
+        columns: Vec<(&'static str, Column)>,
+    }
+
+    impl ParquetWriter {
+        pub fn new(path: PathBuf, manifest: Vec<Field>) -> Self {
+            Self {
+                path,
+                manifest,
+                columns: Default::default(),
+            }
+        }
+    }
+
+    impl RecordWriter for ParquetWriter {
+        fn write(&mut self, record: &[Field]) -> anyhow::Result<()> {
+            let fields = self.manifest.iter().chain(record);
+            if self.columns.is_empty() {
+                self.columns = fields
+                    .clone()
+                    .map(|(name, value)| {
+                        let column = match value {
+                            Value::U64(_) => Column::U64(Default::default()),
+                            Value::F64(_) => Column::F64(Default::default()),
+                            Value::Str(_) => Column::Str(Default::default()),
+                        };
+                        (*name, column)
+                    })
+                    .collect()
+            }
+            for ((_, column), (name, value)) in self.columns.iter_mut().zip(fields) {
+                match (column, value) {
+                    (Column::U64(values), Value::U64(value)) => values.push(*value),
+                    (Column::F64(values), Value::F64(value)) => values.push(*value),
+                    (Column::Str(values), Value::Str(value)) => values.push(value.clone()),
+                    _ => bail!("inconsistent type of field {name}"),
+                }
+            }
+            Ok(())
+        }
+
+        fn finish(self: Box<Self>) -> anyhow::Result<()> {
+            let mut fields = Vec::new();
+            let mut arrays = Vec::<ArrayRef>::new();
+            for (name, column) in self.columns {
+                let (data_type, array) = match column {
+                    Column::U64(values) => (
+                        DataType::UInt64,
+                        Arc::new(UInt64Array::from(values)) as ArrayRef,
+                    ),
+                    Column::F64(values) => {
+                        (DataType::Float64, Arc::new(Float64Array::from(values)) as _)
+                    }
+                    Column::Str(values) => {
+                        (DataType::Utf8, Arc::new(StringArray::from(values)) as _)
+                    }
+                };
+                fields.push(ArrowField::new(name, data_type, false));
+                arrays.push(array)
+            }
+            let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?;
+            let mut writer = ArrowWriter::try_new(File::create(self.path)?, batch.schema(), None)?;
+            writer.write(&batch)?;
+            writer.close()?;
+            Ok(())
+        }
+    }
+}
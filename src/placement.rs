@@ -0,0 +1,149 @@
+use rustc_hash::FxHashMap;
+
+use crate::{NodeId, Overlay, Target};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    // walk away from the target past full nodes, looking at this many closest nodes at most. with
+    // `max_candidate` equal to the replication, a single full node fails the placement
+    NextClosest { max_candidate: usize },
+    // look up the replicas that do not fit around a secondary target derived from the object,
+    // trying this many secondary targets at most
+    Spill { max_target: usize },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    capacity: u64,
+    used: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub num_placed: u64,
+    pub num_failed: u64,
+    // number of placements attempted before (and including) the first failed one
+    pub first_failure: Option<u64>,
+    // replicas that are not stored on one of the closest nodes of the object
+    pub num_displaced: u64,
+}
+
+// replica placement on top of an overlay where every node stores a finite number of replicas
+// a placement is all or nothing: when not every replica finds a node with free space, nothing is
+// stored and the placement counts as failed
+pub struct Placement {
+    overlay: Overlay,
+    slots: FxHashMap<NodeId, Slot>,
+    overflow: Overflow,
+    capacity: u64,
+    used: u64,
+    pub stats: Stats,
+}
+
+impl Placement {
+    // `overlay` should contain exactly the listed nodes, which are (node id, capacity)
+    pub fn new(
+        overlay: Overlay,
+        nodes: impl IntoIterator<Item = (NodeId, u64)>,
+        overflow: Overflow,
+    ) -> Self {
+        let slots = nodes
+            .into_iter()
+            .map(|(node_id, capacity)| (node_id, Slot { capacity, used: 0 }))
+            .collect::<FxHashMap<_, _>>();
+        Self {
+            overlay,
+            capacity: slots.values().map(|slot| slot.capacity).sum(),
+            slots,
+            overflow,
+            used: 0,
+            stats: Default::default(),
+        }
+    }
+
+    pub fn overlay(&self) -> &Overlay {
+        &self.overlay
+    }
+
+    // fraction of the total capacity that is used
+    pub fn fill(&self) -> f64 {
+        self.used as f64 / self.capacity as f64
+    }
+
+    pub fn num_full(&self) -> usize {
+        self.slots
+            .values()
+            .filter(|slot| slot.used == slot.capacity)
+            .count()
+    }
+
+    pub fn place(&mut self, object: Target, replication: usize) -> Option<Vec<NodeId>> {
+        let node_ids = match self.overflow {
+            Overflow::NextClosest { max_candidate } => {
+                let candidates = self.overlay.find(object, max_candidate.max(replication));
+                let mut node_ids = Vec::new();
+                for node_id in candidates {
+                    if node_ids.len() == replication {
+                        break;
+                    }
+                    if self.is_free(node_id) {
+                        node_ids.push(node_id)
+                    }
+                }
+                node_ids
+            }
+            Overflow::Spill { max_target } => {
+                let mut node_ids = Vec::new();
+                for index in 0..=max_target {
+                    let need = replication - node_ids.len();
+                    if need == 0 {
+                        break;
+                    }
+                    let target = if index == 0 {
+                        object
+                    } else {
+                        spill_target(object, index as _)
+                    };
+                    for node_id in self.overlay.find(target, need) {
+                        if self.is_free(node_id) && !node_ids.contains(&node_id) {
+                            node_ids.push(node_id)
+                        }
+                    }
+                }
+                node_ids
+            }
+        };
+        if node_ids.len() < replication {
+            self.stats.num_failed += 1;
+            if self.stats.first_failure.is_none() {
+                self.stats.first_failure = Some(self.stats.num_placed + self.stats.num_failed)
+            }
+            return None;
+        }
+        let closest = self.overlay.find(object, replication);
+        for node_id in &node_ids {
+            self.slots.get_mut(node_id).unwrap().used += 1;
+            if !closest.contains(node_id) {
+                self.stats.num_displaced += 1
+            }
+        }
+        self.used += replication as u64;
+        self.stats.num_placed += 1;
+        Some(node_ids)
+    }
+
+    fn is_free(&self, node_id: NodeId) -> bool {
+        let slot = &self.slots[&node_id];
+        slot.used < slot.capacity
+    }
+}
+
+// the `index`th secondary target of `object`, spread over the whole id space so that spilled
+// replicas do not pile up next to the primary target
+fn spill_target(object: Target, index: u64) -> Target {
+    // splitmix64 finalizer
+    let mut z = object.wrapping_add(index.wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,68 @@
+use std::{
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering::Relaxed},
+    time::{Duration, Instant},
+};
+
+// progress of a run of samples that finish in any order, e.g. on the rayon pool. every finished
+// sample prints a line to stderr with the rate of steps and the time left at that rate
+//
+// samples resumed from a checkpoint count as done but not toward the rate, as they took no time
+#[derive(Debug)]
+pub struct Progress {
+    label: String,
+    step_name: &'static str,
+    num_sample: usize,
+    start: Instant,
+    num_done: AtomicUsize,
+    num_resumed: AtomicUsize,
+    num_step: AtomicU64,
+}
+
+impl Progress {
+    pub fn new(label: impl Into<String>, step_name: &'static str, num_sample: usize) -> Self {
+        Self {
+            label: label.into(),
+            step_name,
+            num_sample,
+            start: Instant::now(),
+            num_done: AtomicUsize::new(0),
+            num_resumed: AtomicUsize::new(0),
+            num_step: AtomicU64::new(0),
+        }
+    }
+
+    pub fn resume(&self) {
+        self.num_resumed.fetch_add(1, Relaxed);
+        let num_done = self.num_done.fetch_add(1, Relaxed) + 1;
+        eprintln!(
+            "{} sample {num_done}/{} resumed",
+            self.label, self.num_sample
+        )
+    }
+
+    pub fn finish(&self, num_step: u64) {
+        let num_step = self.num_step.fetch_add(num_step, Relaxed) + num_step;
+        let num_done = self.num_done.fetch_add(1, Relaxed) + 1;
+        let num_run = num_done
+            .saturating_sub(self.num_resumed.load(Relaxed))
+            .max(1);
+        let elapsed = self.start.elapsed();
+        // the samples run in parallel, so the time per sample is of the wall clock and already
+        // accounts for the number of threads
+        let eta = elapsed.mul_f64((self.num_sample - num_done) as f64 / num_run as f64);
+        eprintln!(
+            "{} sample {num_done}/{} {:.0} {}/s elapsed {} ETA {}",
+            self.label,
+            self.num_sample,
+            num_step as f64 / elapsed.as_secs_f64(),
+            self.step_name,
+            hms(elapsed),
+            hms(eta)
+        )
+    }
+}
+
+fn hms(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
@@ -0,0 +1,57 @@
+use std::str::FromStr;
+
+use anyhow::bail;
+use rand::Rng;
+
+use crate::{NodeId, network::NetworkModel};
+
+// which of the replicas returned by `find` serves a read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadPolicy {
+    // the closest node to the object
+    Primary,
+    Random,
+    // the replica with the lowest rtt to the reading node
+    Nearest,
+}
+
+impl ReadPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Primary => "primary",
+            Self::Random => "random",
+            Self::Nearest => "nearest",
+        }
+    }
+
+    // `replicas` is in the order of `find`, so the primary comes first
+    pub fn select(
+        &self,
+        client: NodeId,
+        replicas: &[NodeId],
+        network: &NetworkModel,
+        rng: &mut impl Rng,
+    ) -> Option<NodeId> {
+        if replicas.is_empty() {
+            return None;
+        }
+        match self {
+            Self::Primary => Some(replicas[0]),
+            Self::Random => Some(replicas[rng.random_range(0..replicas.len())]),
+            Self::Nearest => network.nearest(client, replicas),
+        }
+    }
+}
+
+impl FromStr for ReadPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "primary" => Self::Primary,
+            "random" => Self::Random,
+            "nearest" => Self::Nearest,
+            _ => bail!("unknown read policy {s}"),
+        })
+    }
+}
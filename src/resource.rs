@@ -0,0 +1,114 @@
+use std::str::FromStr;
+
+use anyhow::{bail, ensure};
+use rand_distr::Zipf;
+use serde::{Deserialize, Serialize};
+
+use crate::{Class, NodeId};
+
+// storage and bandwidth are in multiples of the smallest node's, so that both start from 1
+// uptime is the fraction of time the node is online
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Resources {
+    pub storage: u64,
+    pub bandwidth: u64,
+    pub uptime: f64,
+}
+
+impl Resources {
+    pub fn dimensions(&self) -> [(&'static str, f64); 3] {
+        [
+            ("storage", self.storage as _),
+            ("bandwidth", self.bandwidth as _),
+            ("uptime", self.uptime),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClassPolicy {
+    // weighted geometric mean of the dimensions, and the class is its floor log2 as with storage
+    // alone. storage with weight 1 and the rest 0 is `classified::capacity_class`
+    Weighted {
+        storage: f64,
+        bandwidth: f64,
+        uptime: f64,
+    },
+    // the scarcer of storage and bandwidth, discounted by uptime
+    Min,
+}
+
+impl ClassPolicy {
+    pub const STORAGE: Self = Self::Weighted {
+        storage: 1.,
+        bandwidth: 0.,
+        uptime: 0.,
+    };
+    pub const BANDWIDTH: Self = Self::Weighted {
+        storage: 0.,
+        bandwidth: 1.,
+        uptime: 0.,
+    };
+    pub const BALANCED: Self = Self::Weighted {
+        storage: 0.5,
+        bandwidth: 0.5,
+        uptime: 1.,
+    };
+
+    pub fn class(&self, resources: &Resources) -> Class {
+        let storage = log2(resources.storage);
+        let bandwidth = log2(resources.bandwidth);
+        let uptime = resources.uptime.log2();
+        let score = match *self {
+            Self::Weighted {
+                storage: storage_weight,
+                bandwidth: bandwidth_weight,
+                uptime: uptime_weight,
+            } => storage * storage_weight + bandwidth * bandwidth_weight + uptime * uptime_weight,
+            Self::Min => storage.min(bandwidth) + uptime,
+        };
+        score.floor().clamp(0., (NodeId::BITS - 1) as _) as _
+    }
+}
+
+// never rounds up to the next integer, which a plain f64 log2 does just below a power of 2 past
+// 2^53, so that the floor is exactly `ilog2`
+fn log2(value: u64) -> f64 {
+    let Some(floor) = value.checked_ilog2() else {
+        return f64::NEG_INFINITY;
+    };
+    let log2 = floor as f64 + (value as f64 / (1u64 << floor) as f64).log2();
+    log2.min((floor as f64 + 1.).next_down())
+}
+
+// the largest capacity that falls into one of `num_class` classes, i.e. 2^num_class - 1, for up
+// to one class per bit of the id
+pub fn max_capacity(num_class: u8) -> u64 {
+    assert!((1..=NodeId::BITS).contains(&(num_class as u32)));
+    !0 >> (NodeId::BITS - num_class as u32)
+}
+
+// capacities from 1 to `max_capacity(num_class)`. f64 so that the largest capacities are still
+// told apart past 2^24
+pub fn capacity_distr(num_class: u8, skew: f32) -> anyhow::Result<Zipf<f64>> {
+    ensure!(
+        (1..=NodeId::BITS).contains(&(num_class as u32)),
+        "{num_class} classes out of 1 to {}",
+        NodeId::BITS
+    );
+    Ok(Zipf::new(max_capacity(num_class) as _, skew as _)?)
+}
+
+impl FromStr for ClassPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "storage" => Self::STORAGE,
+            "bandwidth" => Self::BANDWIDTH,
+            "balanced" => Self::BALANCED,
+            "min" => Self::Min,
+            _ => bail!("unknown class policy {s}"),
+        })
+    }
+}
@@ -0,0 +1,312 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use hdrhistogram::Histogram;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{Class, NodeId, Overlay, Target};
+
+// virtual time, the unit is up to the experiment
+pub type Time = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Event {
+    Join { node_id: NodeId, class: Class },
+    // the node leaves silently, its replicas stay unavailable until repaired
+    Leave { node_id: NodeId },
+    Put { object: Target },
+    Get { object: Target },
+    // the departure of the node at `since` is noticed, and repair of everything it stored is
+    // scheduled. nothing happens if the node has joined again since then
+    Detect { node_id: NodeId, since: Time },
+    // re-place replicas of the object onto its current `find` set. `since` is when the replica
+    // that triggered the repair was lost
+    Repair { object: Target, since: Time },
+    // record a point of the availability timeline
+    Sample,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub replication: usize,
+    pub detection_delay: Time,
+    pub repair_delay: Time,
+}
+
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub num_get: u64,
+    // gets of objects without any live replica
+    pub num_failed_get: u64,
+    // gets served by a live holder outside the `find` set, as joins do not hand objects off to
+    // the nodes that take over their `find` set
+    pub num_fallback_get: u64,
+    pub num_repair: u64,
+    // replicas copied to a new holder, by puts and repairs
+    pub num_transfer: u64,
+    // objects with no replica left when they are repaired
+    pub num_lost: u64,
+    pub repair_lag: Histogram<u64>,
+    // (time, number of objects with at least one live replica, number of objects not lost)
+    pub availability: Vec<(Time, usize, usize)>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            num_get: 0,
+            num_failed_get: 0,
+            num_fallback_get: 0,
+            num_repair: 0,
+            num_transfer: 0,
+            num_lost: 0,
+            repair_lag: Histogram::new(3).unwrap(),
+            availability: Default::default(),
+        }
+    }
+}
+
+// discrete-event simulation of a replicated store on top of an overlay
+// events at the same time are processed in the order they are scheduled, so a run is a pure
+// function of the initial state and the scheduled events
+pub struct Simulation {
+    now: Time,
+    queue: BinaryHeap<Reverse<(Time, u64, Event)>>,
+    num_scheduled: u64,
+    config: Config,
+    overlay: Overlay,
+    overlay_changed: bool,
+    members: FxHashMap<NodeId, Class>,
+    // every node ever listed as a holder, including departed ones until they are detected
+    holders: FxHashMap<Target, Vec<NodeId>>,
+    stored: FxHashMap<NodeId, FxHashSet<Target>>,
+    // when each node that left and has not been detected yet did so
+    departed: FxHashMap<NodeId, Time>,
+    num_live_replica: FxHashMap<Target, usize>,
+    num_available: usize,
+    pub stats: Stats,
+}
+
+impl Simulation {
+    // `overlay` may already contain nodes, their classes must be listed in `members`
+    pub fn new(
+        overlay: Overlay,
+        members: impl IntoIterator<Item = (NodeId, Class)>,
+        config: Config,
+    ) -> Self {
+        Self {
+            now: 0,
+            queue: Default::default(),
+            num_scheduled: 0,
+            config,
+            overlay,
+            overlay_changed: true,
+            members: members.into_iter().collect(),
+            holders: Default::default(),
+            stored: Default::default(),
+            departed: Default::default(),
+            num_live_replica: Default::default(),
+            num_available: 0,
+            stats: Default::default(),
+        }
+    }
+
+    pub fn now(&self) -> Time {
+        self.now
+    }
+
+    pub fn overlay(&self) -> &Overlay {
+        &self.overlay
+    }
+
+    pub fn num_member(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn schedule(&mut self, delay: Time, event: Event) {
+        self.queue
+            .push(Reverse((self.now + delay, self.num_scheduled, event)));
+        self.num_scheduled += 1
+    }
+
+    pub fn step(&mut self) -> Option<Event> {
+        let Reverse((time, _, event)) = self.queue.pop()?;
+        self.now = time;
+        self.handle(event);
+        Some(event)
+    }
+
+    // process every event scheduled before or at `time`, then advance the clock to `time`
+    pub fn run_until(&mut self, time: Time) {
+        while let Some(Reverse((next_time, _, _))) = self.queue.peek() {
+            if *next_time > time {
+                break;
+            }
+            self.step();
+        }
+        self.now = self.now.max(time)
+    }
+
+    fn find(&mut self, object: Target) -> Vec<NodeId> {
+        if self.overlay_changed {
+            self.overlay.optimize();
+            self.overlay_changed = false
+        }
+        self.overlay.find(object, self.config.replication)
+    }
+
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Join { node_id, class } => {
+                // a node already in keeps its class
+                if self.members.contains_key(&node_id) {
+                    return;
+                }
+                self.members.insert(node_id, class);
+                self.overlay.insert_node(node_id, class);
+                self.overlay_changed = true;
+                // back before its departure is noticed, with everything it stored still there
+                if self.departed.remove(&node_id).is_some() {
+                    for object in self.stored.get(&node_id).into_iter().flatten() {
+                        let num_live_replica = self.num_live_replica.get_mut(object).unwrap();
+                        *num_live_replica += 1;
+                        if *num_live_replica == 1 {
+                            self.num_available += 1
+                        }
+                    }
+                }
+            }
+            Event::Leave { node_id } => {
+                let Some(class) = self.members.remove(&node_id) else {
+                    return;
+                };
+                self.overlay.remove_node(node_id, class);
+                self.overlay_changed = true;
+                for object in self.stored.get(&node_id).into_iter().flatten() {
+                    let num_live_replica = self.num_live_replica.get_mut(object).unwrap();
+                    *num_live_replica -= 1;
+                    if *num_live_replica == 0 {
+                        self.num_available -= 1
+                    }
+                }
+                let since = self.now;
+                self.departed.insert(node_id, since);
+                self.schedule(
+                    self.config.detection_delay,
+                    Event::Detect { node_id, since },
+                )
+            }
+            Event::Put { object } => {
+                if self.holders.contains_key(&object) {
+                    return;
+                }
+                self.holders.insert(object, Default::default());
+                self.num_live_replica.insert(object, 0);
+                self.place(object)
+            }
+            Event::Get { object } => {
+                self.stats.num_get += 1;
+                let found = self.find(object).into_iter().any(|node_id| {
+                    self.stored
+                        .get(&node_id)
+                        .is_some_and(|objects| objects.contains(&object))
+                });
+                if found {
+                    return;
+                }
+                if self
+                    .num_live_replica
+                    .get(&object)
+                    .is_some_and(|&num| num > 0)
+                {
+                    self.stats.num_fallback_get += 1
+                } else {
+                    self.stats.num_failed_get += 1
+                }
+            }
+            Event::Detect { node_id, since } => {
+                // the node may have joined, or joined and left again after `since`
+                if self.departed.get(&node_id) != Some(&since) {
+                    return;
+                }
+                self.departed.remove(&node_id);
+                let Some(objects) = self.stored.remove(&node_id) else {
+                    return;
+                };
+                for object in objects {
+                    self.holders
+                        .get_mut(&object)
+                        .unwrap()
+                        .retain(|&id| id != node_id);
+                    self.schedule(self.config.repair_delay, Event::Repair { object, since })
+                }
+            }
+            Event::Repair { object, since } => {
+                let Some(&num_live_replica) = self.num_live_replica.get(&object) else {
+                    return; // already lost
+                };
+                if num_live_replica == 0 {
+                    self.stats.num_lost += 1;
+                    for node_id in self.holders.remove(&object).unwrap() {
+                        if let Some(objects) = self.stored.get_mut(&node_id) {
+                            objects.remove(&object);
+                        }
+                    }
+                    self.num_live_replica.remove(&object);
+                    return;
+                }
+                self.stats.num_repair += 1;
+                self.stats.repair_lag.record(self.now - since).unwrap();
+                self.place(object)
+            }
+            Event::Sample => self.stats.availability.push((
+                self.now,
+                self.num_available,
+                self.num_live_replica.len(),
+            )),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn assert_consistent(&self) {
+        for (object, holders) in &self.holders {
+            let num_live_replica = holders
+                .iter()
+                .filter(|node_id| self.members.contains_key(node_id))
+                .count();
+            assert_eq!(self.num_live_replica[object], num_live_replica);
+            for node_id in holders {
+                assert!(self.stored[node_id].contains(object))
+            }
+        }
+        for (node_id, objects) in &self.stored {
+            for object in objects {
+                assert!(self.holders[object].contains(node_id))
+            }
+        }
+        let num_available = self
+            .num_live_replica
+            .values()
+            .filter(|&&num_live_replica| num_live_replica > 0)
+            .count();
+        assert_eq!(self.num_available, num_available);
+        assert_eq!(self.num_live_replica.len(), self.holders.len())
+    }
+
+    // copy the object to every node of its `find` set that does not hold it yet
+    fn place(&mut self, object: Target) {
+        for node_id in self.find(object) {
+            let holders = self.holders.get_mut(&object).unwrap();
+            if holders.contains(&node_id) {
+                continue;
+            }
+            holders.push(node_id);
+            self.stored.entry(node_id).or_default().insert(object);
+            self.stats.num_transfer += 1;
+            let num_live_replica = self.num_live_replica.get_mut(&object).unwrap();
+            *num_live_replica += 1;
+            if *num_live_replica == 1 {
+                self.num_available += 1
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::ensure;
+use serde::{Deserialize, Serialize};
+
+use crate::{Class, NodeId, Overlay, resource::Resources};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    pub id: NodeId,
+    pub class: Class,
+    pub resources: Resources,
+}
+
+// a built network: the population together with the overlay it has been inserted into (and
+// optimized, if that's what the experiment does), so loading skips both steps
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub nodes: Vec<Node>,
+    pub overlay: Overlay,
+}
+
+const MAGIC: &[u8; 4] = b"SSNP";
+// bump on every change to the serialized types
+const VERSION: u32 = 3;
+
+impl Snapshot {
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        bincode::serde::encode_into_std_write(self, &mut writer, bincode::config::standard())?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        ensure!(&magic == MAGIC, "not a snapshot file");
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        ensure!(
+            version == VERSION,
+            "unsupported snapshot version {version} (expect {VERSION})"
+        );
+        Ok(bincode::serde::decode_from_std_read(
+            &mut reader,
+            bincode::config::standard(),
+        )?)
+    }
+}
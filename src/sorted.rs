@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{NodeId, Target};
+
+// read-optimized overlay over a sorted array of node ids, bulk built and never mutated after
+// the array is shared, so cloning a built overlay e.g. to hand it to every sample is cheap
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SortedOverlay {
+    node_ids: Arc<[NodeId]>,
+}
+
+impl SortedOverlay {
+    pub fn from_node_ids(mut node_ids: Vec<NodeId>) -> Self {
+        node_ids.sort_unstable();
+        Self {
+            node_ids: node_ids.into(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.node_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node_ids.is_empty()
+    }
+
+    pub fn node_ids(&self) -> &[NodeId] {
+        &self.node_ids
+    }
+
+    pub fn contains(&self, node_id: NodeId) -> bool {
+        self.node_ids.binary_search(&node_id).is_ok()
+    }
+
+    pub fn find(&self, target: Target, count: usize) -> Vec<NodeId> {
+        let mut node_ids = Vec::with_capacity(count.min(self.len()));
+        if count > 0 && !self.is_empty() {
+            self.find_range(target, count, &self.node_ids, &mut node_ids)
+        }
+        node_ids
+    }
+
+    // `range` holds every node sharing some prefix with the target. it splits at the highest bit
+    // that differs inside it, and the half sharing one more bit with the target is strictly closer
+    fn find_range(
+        &self,
+        target: Target,
+        count: usize,
+        range: &[NodeId],
+        node_ids: &mut Vec<NodeId>,
+    ) {
+        let diff = range[0] ^ range[range.len() - 1];
+        if diff == 0 {
+            node_ids.push(range[0]);
+            return;
+        }
+        let level = NodeId::BITS - 1 - diff.leading_zeros();
+        let (zero, one) = range.split_at(range.partition_point(|&id| (id >> level) & 1 == 0));
+        let (primary, secondary) = if (target >> level) & 1 == 0 {
+            (zero, one)
+        } else {
+            (one, zero)
+        };
+        self.find_range(target, count, primary, node_ids);
+        if node_ids.len() < count {
+            self.find_range(target, count, secondary, node_ids)
+        }
+    }
+}
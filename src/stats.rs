@@ -0,0 +1,81 @@
+use rand::Rng;
+
+// a metric over independent samples, e.g. the samples of a run, with 95% confidence intervals of
+// its mean. the t interval assumes the mean to be about normal, which holds for many samples of
+// anything bounded. the bootstrap one only assumes the samples to be representative, but is too
+// narrow for few samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub t_low: f64,
+    pub t_high: f64,
+    pub bootstrap_low: f64,
+    pub bootstrap_high: f64,
+}
+
+impl Summary {
+    // the intervals are NaN for a single sample, and the bootstrap one without resamples
+    pub fn new(samples: &[f64], num_resample: usize, rng: &mut impl Rng) -> Self {
+        assert!(!samples.is_empty());
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let std_dev = (samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.)).sqrt();
+        let half_width = t_quantile(samples.len() - 1) * std_dev / n.sqrt();
+
+        let mut means = (0..num_resample)
+            .map(|_| {
+                (0..samples.len())
+                    .map(|_| samples[rng.random_range(0..samples.len())])
+                    .sum::<f64>()
+                    / n
+            })
+            .collect::<Vec<_>>();
+        means.sort_unstable_by(f64::total_cmp);
+        let percentile = |p: f64| {
+            let index = (means.len().saturating_sub(1) as f64 * p).round() as usize;
+            means.get(index).copied().unwrap_or(f64::NAN)
+        };
+
+        Self {
+            mean,
+            std_dev,
+            t_low: mean - half_width,
+            t_high: mean + half_width,
+            bootstrap_low: percentile(0.025),
+            bootstrap_high: percentile(0.975),
+        }
+    }
+
+    pub fn fields(&self) -> [(&'static str, f64); 6] {
+        [
+            ("mean", self.mean),
+            ("std_dev", self.std_dev),
+            ("t_low", self.t_low),
+            ("t_high", self.t_high),
+            ("bootstrap_low", self.bootstrap_low),
+            ("bootstrap_high", self.bootstrap_high),
+        ]
+    }
+}
+
+// 97.5th percentile of Student's t distribution, i.e. the half width of a two sided 95% interval
+// in standard errors. tabulated up to 30 degrees of freedom, and the Cornish-Fisher expansion
+// around the normal quantile beyond, which is off by less than 1e-4 there
+fn t_quantile(degrees_of_freedom: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    if degrees_of_freedom == 0 {
+        return f64::NAN;
+    }
+    if let Some(t) = TABLE.get(degrees_of_freedom - 1) {
+        return *t;
+    }
+    let (z, v) = (1.959964f64, degrees_of_freedom as f64);
+    z + (z.powi(3) + z) / (4. * v)
+        + (5. * z.powi(5) + 16. * z.powi(3) + 3. * z) / (96. * v.powi(2))
+        + (3. * z.powi(7) + 19. * z.powi(5) + 17. * z.powi(3) - 15. * z) / (384. * v.powi(3))
+}
@@ -1,8 +1,24 @@
 use std::collections::{HashMap, HashSet};
 
 use proptest::{prelude::*, sample::SizeRange, test_runner::FileFailurePersistence};
+use rand::{SeedableRng, rngs::StdRng};
 
-use crate::{BinOverlay, Classified, NodeId, Target, TrieOverlay, classified, find};
+use crate::{
+    BinOverlay, Class, Classified, CompactTrieOverlay, Error, NodeId, Overlay, SortedOverlay,
+    Target, TrieOverlay, UnifiedOverlay,
+    adversary::Position,
+    classified,
+    fairness::Fairness,
+    find, identity, keyspace, model,
+    network::{Coordinate, LatencyMatrix, Link, Location, NetworkModel, Transfer},
+    output::{self, Format, Manifest, Value},
+    placement::{Overflow, Placement},
+    read::ReadPolicy,
+    resource::{self, ClassPolicy, Resources},
+    sim::{self, Event, Simulation},
+    snapshot::{self, Snapshot},
+    stats::Summary,
+};
 
 fn common_config(cases: u32) -> ProptestConfig {
     ProptestConfig {
@@ -36,17 +52,20 @@ proptest! {
         let mut compressed_trie = trie.clone();
         compressed_trie.compress();
         let mut node_ids = node_ids.into_iter().collect::<Vec<_>>();
+        let compact_trie = CompactTrieOverlay::from_node_ids(node_ids.clone());
+        let sorted = SortedOverlay::from_node_ids(node_ids.clone());
         for count in 1..node_ids.len() {
             let ground_truth = find(&mut node_ids, target, count);
             let results = trie.find(target, count);
-            assert_eq!(results.len(), count);
-            assert!(ground_truth.iter().all(|id| results.contains(id)));
+            assert_eq!(results, ground_truth);
             let results = compressed_trie.find(target, count);
-            assert_eq!(results.len(), count);
-            assert!(ground_truth.iter().all(|id| results.contains(id)));
+            assert_eq!(results, ground_truth);
             let results = bin.find(target, count);
-            assert_eq!(results.len(), count);
-            assert!(ground_truth.iter().all(|id| results.contains(id)))
+            assert_eq!(results, ground_truth);
+            let results = compact_trie.find(target, count);
+            assert_eq!(results, ground_truth);
+            let results = sorted.find(target, count);
+            assert_eq!(results, ground_truth)
         }
     }
 }
@@ -56,23 +75,18 @@ proptest! {
     #[test]
     fn classified_overlay_find(node_ids in classified_node_ids(), target: Target) {
         let mut overlay = Classified::new();
-        let mut distances = Vec::new();
-        let mut node_classes = HashMap::new();
-        for (node_id, class) in node_ids {
-            node_classes.insert(node_id, class);
-            overlay.insert_node(node_id, class);
-            distances.push(classified::distance(node_id, target, class))
+        for &(node_id, class) in &node_ids {
+            overlay.insert_node(node_id, class)
         }
         let mut optimized_overlay = overlay.clone();
         optimized_overlay.optimize();
-        distances.sort_unstable();
-        for count in 1..distances.len() {
-            let results = overlay.find(target, count);
-            assert_eq!(results.len(), count);
-            assert!(results.into_iter().all(|id| classified::distance(id, target, node_classes[&id]) <= distances[count - 1]));
-            let results = optimized_overlay.find(target, count);
-            assert_eq!(results.len(), count);
-            assert!(results.into_iter().all(|id| classified::distance(id, target, node_classes[&id]) <= distances[count - 1]))
+        let mut node_ids = node_ids.into_iter().collect::<Vec<_>>();
+        let unified_overlay = UnifiedOverlay::from_classified_node_ids(node_ids.clone());
+        for count in 1..node_ids.len() {
+            let ground_truth = classified::find(&mut node_ids, target, count);
+            assert_eq!(overlay.find(target, count), ground_truth);
+            assert_eq!(optimized_overlay.find(target, count), ground_truth);
+            assert_eq!(unified_overlay.find(target, count), ground_truth)
         }
     }
 }
@@ -90,3 +104,686 @@ proptest! {
         overlay.assert_compressed()
     }
 }
+
+proptest! {
+    #![proptest_config(common_config(1 << 6))]
+    #[test]
+    fn snapshot_round_trip(node_ids in classified_node_ids(), targets: Vec<Target>) {
+        let mut overlay = Classified::new();
+        let mut nodes = Vec::new();
+        for (node_id, class) in node_ids {
+            overlay.insert_node(node_id, class);
+            nodes.push(snapshot::Node {
+                id: node_id,
+                class,
+                resources: Resources { storage: 1 << class, bandwidth: 1, uptime: 1. },
+            })
+        }
+        overlay.optimize();
+        let snapshot = Snapshot { nodes, overlay: Overlay::Classified(overlay) };
+        let path = std::env::temp_dir().join(format!("snapshot-{}.bin", std::process::id()));
+        snapshot.save(&path).unwrap();
+        let loaded = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.nodes, snapshot.nodes);
+        for target in targets {
+            assert_eq!(loaded.overlay.find(target, 3), snapshot.overlay.find(target, 3))
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn output_round_trip(records in prop::collection::vec((any::<u64>(), -1e9..1e9f64, "[a-z,\"\n ]{0,8}"), 0..20)) {
+        for format in [Format::Csv, Format::JsonLines] {
+            let path = std::env::temp_dir().join(format!("output-{}", std::process::id()));
+            let mut writer = format.create(&path, &Manifest::new(0).param("name", "a,\"b\"")).unwrap();
+            for &(count, rate, ref label) in &records {
+                writer.write(&[("count", count.into()), ("rate", rate.into()), ("label", label.as_str().into())]).unwrap()
+            }
+            writer.finish().unwrap();
+            let path = path.with_extension(format.extension());
+            let read = output::read_records(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(read.len(), records.len());
+            for (record, (count, rate, label)) in read.iter().zip(&records) {
+                let field = |name| &record.iter().find(|(field, _)| field == name).unwrap().1;
+                assert_eq!(field("name").as_str(), Some("a,\"b\""));
+                assert_eq!(field("count"), &Value::U64(*count));
+                assert_eq!(field("rate").as_f64(), Some(*rate));
+                // a label of digits reads back as a number from CSV
+                assert_eq!(field("label").as_str().unwrap_or(label), label)
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 10))]
+    #[test]
+    fn fairness_bounds(loads in prop::collection::vec((0..1000u64, 1..1000u64), 1..100)) {
+        prop_assume!(loads.iter().any(|&(hit, _)| hit > 0));
+        let fairness = Fairness::new(&loads);
+        assert!((0. ..1.).contains(&fairness.gini));
+        assert!(fairness.jain > 0. && fairness.jain <= 1. + 1e-9);
+        assert!(fairness.max_mean_ratio >= 1. - 1e-9);
+        assert!(fairness.coefficient_of_variation >= 0.);
+        assert!((0. ..=2. + 1e-9).contains(&fairness.earth_mover_distance));
+        // hits exactly proportional to capacity
+        let fair_loads = loads.iter().map(|&(_, capacity)| (capacity * 3, capacity)).collect::<Vec<_>>();
+        let fairness = Fairness::new(&fair_loads);
+        assert!(fairness.gini.abs() < 1e-9);
+        assert!((fairness.jain - 1.).abs() < 1e-9);
+        assert!(fairness.earth_mover_distance < 1e-9)
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn stats_summary(samples in prop::collection::vec(-1000. ..1000f64, 2..100), seed: u64) {
+        let summary = Summary::new(&samples, 1000, &mut StdRng::seed_from_u64(seed));
+        let min = samples.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = samples.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        assert!(summary.t_low <= summary.mean && summary.mean <= summary.t_high);
+        // up to the rounding of the resampled means
+        assert!(min - 1e-9 <= summary.bootstrap_low && summary.bootstrap_low <= summary.bootstrap_high);
+        assert!(summary.bootstrap_high <= max + 1e-9);
+        // the t interval is wider, as it accounts for the uncertainty of the standard deviation
+        let bootstrap_width = summary.bootstrap_high - summary.bootstrap_low;
+        assert!(summary.t_high - summary.t_low >= bootstrap_width * 0.8);
+        let same = Summary::new(&[samples[0]; 10], 1000, &mut StdRng::seed_from_u64(seed));
+        let tolerance = samples[0].abs() * 1e-12;
+        assert!((same.t_high - same.t_low).abs() <= tolerance && (same.bootstrap_high - samples[0]).abs() <= tolerance)
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn overlay_remove(node_ids in classified_node_ids(), removed in prop::collection::vec(any::<prop::sample::Index>(), 0..50), target: Target) {
+        let node_ids = node_ids.into_iter().collect::<Vec<_>>();
+        prop_assume!(!node_ids.is_empty());
+        let mut trie = TrieOverlay::new();
+        let mut bin = BinOverlay::new();
+        let mut overlay = Classified::new();
+        for &(node_id, class) in &node_ids {
+            trie.insert_node(node_id);
+            bin.insert_node(node_id);
+            overlay.insert_node(node_id, class)
+        }
+        overlay.optimize();
+        let mut remaining = node_ids.iter().copied().collect::<HashMap<_, _>>();
+        for index in removed {
+            let (node_id, class) = *index.get(&node_ids);
+            let present = remaining.remove(&node_id).is_some();
+            assert_eq!(trie.remove_node(node_id), present);
+            assert_eq!(bin.remove_node(node_id), present);
+            assert_eq!(overlay.remove_node(node_id, class), present)
+        }
+        let mut remaining_ids = remaining.keys().copied().collect::<Vec<_>>();
+        let mut remaining = remaining.into_iter().collect::<Vec<_>>();
+        for count in 1..=remaining_ids.len() {
+            let ground_truth = find(&mut remaining_ids, target, count);
+            assert_eq!(trie.find(target, count), ground_truth);
+            assert_eq!(bin.find(target, count), ground_truth);
+            assert_eq!(overlay.find(target, count), classified::find(&mut remaining, target, count))
+        }
+    }
+
+    #[test]
+    fn placement_capacity(node_ids in classified_node_ids(), objects in prop::collection::vec(any::<Target>(), 1..200), spill: bool) {
+        prop_assume!(node_ids.len() >= 3);
+        let mut overlay = Classified::new();
+        let mut capacities = HashMap::new();
+        for &(node_id, class) in &node_ids {
+            overlay.insert_node(node_id, class);
+            capacities.insert(node_id, node_id % 4);
+        }
+        overlay.optimize();
+        let overflow = if spill { Overflow::Spill { max_target: 4 } } else { Overflow::NextClosest { max_candidate: 8 } };
+        let mut placement = Placement::new(
+            Overlay::Classified(overlay),
+            node_ids.iter().map(|&(node_id, _)| (node_id, capacities[&node_id])),
+            overflow,
+        );
+        let mut used = HashMap::<NodeId, u64>::new();
+        for object in objects {
+            if let Some(placed) = placement.place(object, 3) {
+                assert_eq!(placed.len(), 3);
+                assert_eq!(placed.iter().collect::<HashSet<_>>().len(), 3);
+                for node_id in placed {
+                    *used.entry(node_id).or_default() += 1
+                }
+            }
+        }
+        assert!(used.iter().all(|(node_id, &used)| used <= capacities[node_id]));
+        let total = capacities.values().sum::<u64>();
+        if total > 0 {
+            assert_eq!(placement.fill(), placement.stats.num_placed as f64 * 3. / total as f64)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Insert(NodeId, Class),
+    Remove(prop::sample::Index),
+    UpdateClass(prop::sample::Index, Class),
+    Optimize,
+    Compress,
+    Find(Target, usize),
+}
+
+// ids that are either uniform or share a long run of leading zeros, so that tries get deep and
+// nodes differ in the lowest bits only
+fn clustered_id() -> impl Strategy<Value = NodeId> {
+    prop_oneof![
+        any::<NodeId>(),
+        (any::<NodeId>(), 0..NodeId::BITS).prop_map(|(id, shift)| id >> shift),
+    ]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (clustered_id(), 0..NodeId::BITS as Class).prop_map(|(node_id, class)| Op::Insert(node_id, class)),
+        2 => any::<prop::sample::Index>().prop_map(Op::Remove),
+        2 => (any::<prop::sample::Index>(), 0..NodeId::BITS as Class).prop_map(|(index, class)| Op::UpdateClass(index, class)),
+        1 => Just(Op::Optimize),
+        1 => Just(Op::Compress),
+        3 => (clustered_id(), 0..20usize).prop_map(|(target, count)| Op::Find(target, count)),
+    ]
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 10))]
+    #[test]
+    fn overlay_operations(ops in prop::collection::vec(op(), 0..200)) {
+        let mut members = Vec::<(NodeId, Class)>::new();
+        let mut trie = TrieOverlay::new();
+        let mut bin = BinOverlay::new();
+        let mut overlay = Overlay::Classified(Classified::new());
+        for op in ops {
+            match op {
+                Op::Insert(node_id, class) => {
+                    if members.iter().any(|&(id, _)| id == node_id) {
+                        let duplicate = Err(Error::DuplicateNode(node_id));
+                        assert_eq!(trie.try_insert_node(node_id), duplicate);
+                        assert_eq!(bin.try_insert_node(node_id), duplicate);
+                        assert_eq!(overlay.try_insert_node(node_id, class), duplicate);
+                        continue;
+                    }
+                    members.push((node_id, class));
+                    trie.try_insert_node(node_id).unwrap();
+                    bin.try_insert_node(node_id).unwrap();
+                    overlay.try_insert_node(node_id, class).unwrap();
+                    assert!(trie.contains(node_id) && bin.contains(node_id) && overlay.contains(node_id))
+                }
+                Op::Remove(index) => {
+                    if members.is_empty() {
+                        continue;
+                    }
+                    let (node_id, class) = members.swap_remove(index.index(members.len()));
+                    assert!(trie.remove_node(node_id));
+                    assert!(bin.remove_node(node_id));
+                    assert!(overlay.remove_node(node_id, class));
+                    assert!(!trie.remove_node(node_id));
+                    assert!(!bin.remove_node(node_id));
+                    assert!(!overlay.remove_node(node_id, class))
+                }
+                Op::UpdateClass(index, class) => {
+                    if members.is_empty() {
+                        assert!(!overlay.update_class(0, class));
+                        continue;
+                    }
+                    let index = index.index(members.len());
+                    let member = &mut members[index];
+                    member.1 = class;
+                    assert!(overlay.update_class(member.0, class))
+                }
+                Op::Optimize => overlay.optimize(),
+                Op::Compress => trie.compress(),
+                Op::Find(target, count) => {
+                    let mut node_ids = members.iter().map(|&(id, _)| id).collect::<Vec<_>>();
+                    let ground_truth = find(&mut node_ids, target, count);
+                    assert_eq!(trie.find(target, count), ground_truth);
+                    assert_eq!(bin.find(target, count), ground_truth);
+                    assert_eq!(CompactTrieOverlay::from_node_ids(node_ids.clone()).find(target, count), ground_truth);
+                    assert_eq!(SortedOverlay::from_node_ids(node_ids).find(target, count), ground_truth);
+                    let ground_truth = classified::find(&mut members.clone(), target, count);
+                    assert_eq!(overlay.find(target, count), ground_truth);
+                    assert_eq!(UnifiedOverlay::from_classified_node_ids(members.clone()).find(target, count), ground_truth);
+                    if count <= members.len() {
+                        assert_eq!(overlay.try_find(target, count), Ok(ground_truth))
+                    } else {
+                        assert_eq!(overlay.try_find(target, count), Err(Error::NotEnoughNode { count, found: members.len() }))
+                    }
+                }
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn model_total_hit_rate(num_nodes in prop::collection::vec(0..100usize, 1..8), count in 0..5usize) {
+        let total = num_nodes.iter().sum::<usize>();
+        prop_assume!(total > 0);
+        // every find returns `count` nodes, or all of them
+        let expected = count.min(total) as f64;
+        let rates = model::class_hit_rate(&num_nodes, count);
+        let sum = num_nodes.iter().zip(&rates).map(|(&n, rate)| n as f64 * rate).sum::<f64>();
+        assert!((sum - expected).abs() <= 1e-3 * expected, "{sum} {expected}")
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn capacity_class_range(num_class in 1..=NodeId::BITS as Class, capacity: u64) {
+        let max_capacity = resource::max_capacity(num_class);
+        assert_eq!(classified::capacity_class(max_capacity), num_class - 1);
+        let capacity = capacity % max_capacity + 1;
+        assert!(classified::capacity_class(capacity) < num_class);
+        assert!(resource::capacity_distr(num_class, 1.).is_ok());
+        assert!(resource::capacity_distr(0, 1.).is_err() && resource::capacity_distr(NodeId::BITS as Class + 1, 1.).is_err())
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 10))]
+    #[test]
+    fn class_policy(storage in 1..=u64::MAX, bandwidth in 1..=u64::MAX, uptime in 0.01..=1.) {
+        let policies = ["storage", "bandwidth", "balanced", "min"].map(|name| name.parse::<ClassPolicy>().unwrap());
+        assert_eq!(policies, [ClassPolicy::STORAGE, ClassPolicy::BANDWIDTH, ClassPolicy::BALANCED, ClassPolicy::Min]);
+        assert!("uptime".parse::<ClassPolicy>().is_err());
+
+        let resources = Resources { storage, bandwidth, uptime };
+        let [storage_class, bandwidth_class, balanced_class, min_class] = policies.map(|policy| policy.class(&resources));
+        assert_eq!(storage_class, classified::capacity_class(storage));
+        assert_eq!(bandwidth_class, classified::capacity_class(bandwidth));
+        // the minimum is below the mean, and an uptime below 1 only takes away
+        assert!(min_class <= balanced_class);
+        assert!(balanced_class <= storage_class.max(bandwidth_class));
+        assert!(min_class <= storage_class.min(bandwidth_class));
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 6))]
+    #[test]
+    fn class_policy_boundary(class in 1..NodeId::BITS as Class) {
+        // at a power of 2 every policy is at that class with full uptime, and just below it one
+        // class lower
+        for policy in [ClassPolicy::STORAGE, ClassPolicy::BANDWIDTH, ClassPolicy::BALANCED, ClassPolicy::Min] {
+            let at = Resources { storage: 1 << class, bandwidth: 1 << class, uptime: 1. };
+            assert_eq!(policy.class(&at), class);
+            let below = Resources { storage: (1 << class) - 1, bandwidth: (1 << class) - 1, uptime: 1. };
+            assert_eq!(policy.class(&below), class - 1);
+        }
+        // half the uptime takes a whole class from policies that count it
+        let halved = Resources { storage: 1 << class, bandwidth: 1 << class, uptime: 0.5 };
+        assert_eq!(ClassPolicy::STORAGE.class(&halved), class);
+        assert_eq!(ClassPolicy::BALANCED.class(&halved), class - 1);
+        assert_eq!(ClassPolicy::Min.class(&halved), class - 1);
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 6))]
+    #[test]
+    fn identity_grind(target: Target, class in 0..NodeId::BITS as Class, num_attempt in 1..64u32, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (public_key, node_id) = identity::grind(target, class, num_attempt, &mut rng);
+        assert_eq!(identity::node_id(&public_key), node_id);
+        // the key kept is the closest of the ones drawn
+        let mut rng = StdRng::seed_from_u64(seed);
+        for _ in 0..num_attempt {
+            let other = identity::node_id(&identity::random_key(&mut rng));
+            assert!(classified::distance(node_id, target, class) <= classified::distance(other, target, class))
+        }
+    }
+}
+
+// the exact partition is only practical for the few classes of the experiments
+fn few_class_node_ids() -> impl Strategy<Value = Vec<classified::NodeId>> {
+    prop::collection::hash_map(any::<NodeId>(), 0..8 as Class, 0..100)
+        .prop_map(|node_ids| node_ids.into_iter().collect())
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn keyspace_partition(node_ids in few_class_node_ids(), targets: Vec<Target>, count in 1..5usize) {
+        let mut ranges = Vec::new();
+        keyspace::partition(&node_ids, count, |range| ranges.push(range));
+        let width = ranges.iter().map(keyspace::Range::width).sum::<f64>();
+        assert!(ranges.is_empty() || (width - 1.).abs() < 1e-9);
+        for target in targets {
+            let mut ground_truth = classified::find(&mut node_ids.clone(), target, count);
+            ground_truth.sort_unstable();
+            let Some(range) = ranges.iter().find(|range| range.contains(target)) else {
+                assert!(ground_truth.is_empty());
+                continue;
+            };
+            assert_eq!(range.node_ids, ground_truth)
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn keyspace_join(before in few_class_node_ids(), node_id: NodeId, class in 0..8 as Class, count in 1..5usize) {
+        prop_assume!(before.len() >= count && before.iter().all(|&(id, _)| id != node_id));
+        let mut after = before.clone();
+        after.push((node_id, class));
+        // the results change exactly where the new node is found, and gain only it
+        let mut owned = 0.;
+        keyspace::partition(&after, count, |range| {
+            if range.node_ids.contains(&node_id) {
+                owned += range.width()
+            }
+        });
+        let change = keyspace::change(&before, &after, count);
+        assert!((change.changed - owned).abs() < 1e-12);
+        assert!((change.moved - owned / count as f64).abs() < 1e-12);
+        // and leaving is the reverse
+        let change = keyspace::change(&after, &before, count);
+        assert!((change.changed - owned).abs() < 1e-12);
+        assert!((change.moved - owned / count as f64).abs() < 1e-12)
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn keyspace_ownership(node_ids in few_class_node_ids(), count in 1..5usize) {
+        let ownership = keyspace::ownership(&node_ids, count);
+        assert_eq!(ownership.len(), node_ids.len());
+        let total = ownership.values().sum::<f64>();
+        assert!((total - count.min(node_ids.len()) as f64).abs() < 1e-9);
+        assert!(ownership.values().all(|&share| (0. ..=1.).contains(&share)))
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 4))]
+    #[test]
+    fn keyspace_sampled_ownership(node_ids in few_class_node_ids(), count in 1..5usize, seed: u64) {
+        let mut overlay = Overlay::Classified(Classified::new());
+        for &(node_id, class) in &node_ids {
+            overlay.insert_node(node_id, class);
+        }
+        overlay.optimize();
+        let ownership = keyspace::ownership(&node_ids, count);
+        let ids = node_ids.iter().map(|&(id, _)| id).collect::<Vec<_>>();
+        let sampled = keyspace::sampled_ownership(&overlay, &ids, count, 20_000, &mut StdRng::seed_from_u64(seed));
+        // more than 7 standard deviations of the mean over the targets
+        for (node_id, share) in ownership {
+            assert!((sampled[&node_id] - share).abs() < 0.025, "{node_id} {share} {}", sampled[&node_id])
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn keyspace_update_class(before in few_class_node_ids(), index: prop::sample::Index, class in 0..8 as Class, count in 1..5usize) {
+        prop_assume!(before.len() > count);
+        let index = index.index(before.len());
+        let mut after = before.clone();
+        after[index].1 = class;
+        // a node only gains targets when it moves up and only loses them when it moves down, and
+        // others take over exactly what it loses
+        let node_id = before[index].0;
+        let owned = |node_ids: &[classified::NodeId]| keyspace::ownership(node_ids, count)[&node_id];
+        let change = keyspace::change(&before, &after, count);
+        assert!((change.moved - (owned(&after) - owned(&before)).abs() / count as f64).abs() < 1e-12)
+    }
+}
+
+fn sim_config() -> sim::Config {
+    sim::Config {
+        replication: 3,
+        detection_delay: 10,
+        repair_delay: 5,
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 6))]
+    #[test]
+    fn sim_rejoin(node_ids in few_class_node_ids(), objects: HashSet<Target>) {
+        let mut simulation = Simulation::new(Overlay::Classified(Classified::new()), [], sim_config());
+        for &(node_id, class) in &node_ids {
+            simulation.schedule(0, Event::Join { node_id, class })
+        }
+        for &object in &objects {
+            simulation.schedule(1, Event::Put { object })
+        }
+        simulation.run_until(1);
+        simulation.assert_consistent();
+        // every node leaves and joins again before its departure is detected, then leaves for good
+        for &(node_id, class) in &node_ids {
+            simulation.schedule(1, Event::Leave { node_id });
+            simulation.schedule(2, Event::Join { node_id, class });
+            simulation.schedule(3, Event::Leave { node_id });
+        }
+        for time in 2..=30 {
+            simulation.run_until(time);
+            simulation.assert_consistent()
+        }
+        assert_eq!(simulation.num_member(), 0)
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 6))]
+    #[test]
+    fn sim_join_closer(node_ids in few_class_node_ids(), object: Target, class in 0..8 as Class) {
+        let replication = sim_config().replication;
+        prop_assume!(node_ids.len() >= replication);
+        let mut simulation = Simulation::new(Overlay::Classified(Classified::new()), [], sim_config());
+        for &(node_id, class) in &node_ids {
+            simulation.schedule(0, Event::Join { node_id, class })
+        }
+        simulation.schedule(1, Event::Put { object });
+        simulation.run_until(1);
+        // newcomers right at the object take over its whole `find` set, while every holder is
+        // still live
+        let newcomers = (0..replication as Target).map(|i| object ^ i).collect::<HashSet<_>>();
+        prop_assume!(node_ids.iter().all(|(node_id, _)| !newcomers.contains(node_id)));
+        for &node_id in &newcomers {
+            simulation.schedule(1, Event::Join { node_id, class })
+        }
+        simulation.run_until(2);
+        prop_assume!(simulation.overlay().find(object, replication).into_iter().all(|node_id| newcomers.contains(&node_id)));
+        simulation.schedule(1, Event::Get { object });
+        simulation.run_until(3);
+        simulation.assert_consistent();
+        assert_eq!(simulation.stats.num_get, 1);
+        assert_eq!(simulation.stats.num_failed_get, 0);
+        assert_eq!(simulation.stats.num_fallback_get, 1)
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn sim_consistent(events in prop::collection::vec((0..4u8, 0..8 as NodeId, 0..8 as Class, 0..16u64), 0..200)) {
+        let mut simulation = Simulation::new(Overlay::Classified(Classified::new()), [], sim_config());
+        // few node ids and objects, so that the same ones come back often
+        for (time, &(kind, node_id, class, delay)) in events.iter().enumerate() {
+            let event = match kind {
+                0 => Event::Join { node_id, class },
+                1 => Event::Leave { node_id },
+                2 => Event::Put { object: delay },
+                _ => Event::Get { object: delay },
+            };
+            simulation.schedule(delay, event);
+            simulation.run_until(time as _);
+            simulation.assert_consistent()
+        }
+        simulation.run_until(events.len() as u64 + 100);
+        simulation.assert_consistent()
+    }
+}
+
+fn latency_matrix() -> impl Strategy<Value = Vec<Vec<i64>>> {
+    (1..6usize).prop_flat_map(|num_site| {
+        // negative entries are missing measurements
+        prop::collection::vec(prop::collection::vec(-1..1_000_000i64, num_site), num_site)
+    })
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn network_latency_matrix(rtt in latency_matrix()) {
+        let path = std::env::temp_dir().join(format!("latency-{}.txt", std::process::id()));
+        let text = rtt.iter().map(|row| row.iter().map(i64::to_string).collect::<Vec<_>>().join(" ")).collect::<Vec<_>>().join("\n");
+        std::fs::write(&path, &text).unwrap();
+        let matrix = LatencyMatrix::load(&path).unwrap();
+        std::fs::write(&path, format!("{text} 1")).unwrap();
+        let not_square = LatencyMatrix::load(&path);
+        // the first entry is not a number
+        let first_end = text.find(char::is_whitespace).unwrap_or(text.len());
+        std::fs::write(&path, format!("1ms{}", &text[first_end..])).unwrap();
+        let not_number = LatencyMatrix::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(not_square.is_err());
+        assert!(not_number.is_err());
+
+        let num_site = rtt.len();
+        assert_eq!(matrix.num_site(), num_site);
+        let known = rtt.iter().flatten().filter(|&&rtt| rtt >= 0).collect::<Vec<_>>();
+        let mean = known.iter().copied().sum::<i64>() as f64 / known.len().max(1) as f64 / 1e6;
+        for (a, row) in rtt.iter().enumerate() {
+            for (b, &value) in row.iter().enumerate() {
+                let expected = if value >= 0 {
+                    value as f64 / 1e6
+                } else if rtt[b][a] >= 0 {
+                    rtt[b][a] as f64 / 1e6
+                } else {
+                    mean
+                };
+                assert!((matrix.rtt(a, b) - expected).abs() < 1e-12)
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn network_complete(upload in 1e3..1e9f64, downloads in prop::collection::vec(1e3..1e9f64, 1..8), size in 0.0..1e9f64, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut network = NetworkModel::new(None);
+        let link = |upload, download, rng: &mut StdRng| Link {
+            location: Location::Coordinate(Coordinate::synthetic(rng)),
+            upload,
+            download,
+        };
+        network.insert_node(0, link(upload, upload, &mut rng)).unwrap();
+        assert!(network.insert_node(0, Link { location: Location::Site(0), upload, download: upload }).is_err());
+        let mut transfers = Vec::new();
+        for (client, &download) in (1..).zip(&downloads) {
+            network.insert_node(client, link(download, download, &mut rng)).unwrap();
+            transfers.push(Transfer { client, server: 0, size })
+        }
+        // the server splits its upload among every client, each of which downloads one transfer
+        let share = upload / downloads.len() as f64;
+        for (transfer, time) in transfers.iter().zip(network.complete(&transfers)) {
+            let rate = share.min(network.link(transfer.client).download);
+            let expected = network.rtt(transfer.client, 0) + size / rate;
+            assert!((time - expected).abs() <= expected * 1e-12);
+            assert!(time >= network.rtt(transfer.client, 0) + size / upload)
+        }
+
+        let path = std::env::temp_dir().join(format!("latency-sites-{}.txt", std::process::id()));
+        std::fs::write(&path, "0 1\n1 0").unwrap();
+        let matrix = LatencyMatrix::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // by coordinate with a matrix, or at a site out of it
+        let mut network = NetworkModel::new(Some(matrix));
+        assert!(network.insert_node(0, link(upload, upload, &mut rng)).is_err());
+        assert!(network.insert_node(0, Link { location: Location::Site(2), upload, download: upload }).is_err());
+        network.insert_node(0, Link { location: Location::Site(1), upload, download: upload }).unwrap()
+    }
+}
+
+// largest difference of the empirical CDFs of the two samples, which are sorted
+fn ks_distance(a: &[Target], b: &[Target]) -> f64 {
+    a.iter()
+        .chain(b)
+        .map(|&x| {
+            let cdf = |sample: &[Target]| {
+                sample.partition_point(|&y| y <= x) as f64 / sample.len() as f64
+            };
+            (cdf(a) - cdf(b)).abs()
+        })
+        .fold(0., f64::max)
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 4))]
+    #[test]
+    fn adversary_targeted(target: Target, class in 0..NodeId::BITS as Class, grind in 1..8u32, seed: u64) {
+        const NUM_NODE: usize = 10_000;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let position = Position::Targeted { targets: vec![target], grind };
+        let mut sampled = (0..NUM_NODE)
+            .map(|index| classified::distance(position.node_id(index, class, &mut rng), target, class))
+            .collect::<Vec<_>>();
+        let mut ground = (0..NUM_NODE)
+            .map(|_| classified::distance(identity::grind(target, class, grind, &mut rng).1, target, class))
+            .collect::<Vec<_>>();
+        sampled.sort_unstable();
+        ground.sort_unstable();
+        // the two samples are of the same distribution unless the distance is beyond the critical
+        // value of the two sample Kolmogorov-Smirnov test at 1e-6
+        let critical = (-(1e-6f64 / 2.).ln() / 2.).sqrt() * (2. / NUM_NODE as f64).sqrt();
+        let distance = ks_distance(&sampled, &ground);
+        assert!(distance < critical, "{distance} {critical}")
+    }
+}
+
+proptest! {
+    #![proptest_config(common_config(1 << 8))]
+    #[test]
+    fn read_policy(node_ids in few_class_node_ids(), target: Target, count in 1..6usize, seed: u64) {
+        prop_assume!(!node_ids.is_empty());
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut overlay = Overlay::Classified(Classified::new());
+        let mut network = NetworkModel::new(None);
+        for &(node_id, class) in &node_ids {
+            overlay.insert_node(node_id, class);
+            let location = Location::Coordinate(Coordinate::synthetic(&mut rng));
+            network.insert_node(node_id, Link { location, upload: 1e6, download: 1e7 }).unwrap()
+        }
+        let policies = [ReadPolicy::Primary, ReadPolicy::Random, ReadPolicy::Nearest];
+        for policy in policies {
+            assert_eq!(policy.name().parse::<ReadPolicy>().unwrap(), policy);
+            assert_eq!(policy.select(node_ids[0].0, &[], &network, &mut rng), None)
+        }
+
+        let replicas = overlay.find(target, count);
+        for &(client, _) in &node_ids {
+            let select = |policy: ReadPolicy, rng: &mut StdRng| policy.select(client, &replicas, &network, rng).unwrap();
+            assert_eq!(select(ReadPolicy::Primary, &mut rng), replicas[0]);
+            let nearest = select(ReadPolicy::Nearest, &mut rng);
+            assert!(replicas.contains(&nearest));
+            for &replica in &replicas {
+                assert!(network.rtt(client, nearest) <= network.rtt(client, replica))
+            }
+            assert!(replicas.contains(&select(ReadPolicy::Random, &mut rng)))
+        }
+        // every replica is read at random now and then, missing one of 5 in 200 reads is less
+        // likely than 1e-18
+        let read = (0..200).map(|_| ReadPolicy::Random.select(node_ids[0].0, &replicas, &network, &mut rng).unwrap()).collect::<HashSet<_>>();
+        assert_eq!(read.len(), replicas.len())
+    }
+}
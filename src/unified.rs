@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Class, Distance, NodeId, Target, classified,
+    compact::{BUCKET_LEVEL, Fork, build},
+};
+
+// a single index over the nodes of every class, bulk built from a classified node list
+// the nodes of each class are laid out as in `CompactTrieOverlay::from_classified_node_ids`,
+// keyed by `id << class` i.e. the bits the class does not ignore, one class after another. a query
+// walks the classes against one shared bound, the distance of the `count`th closest node so far,
+// so a range of any class is skipped once it cannot hold one of the closest nodes, instead of
+// every class being searched to the full count and the results merged and sorted. the classes
+// are walked in an order fixed at build time, the densest in the target space first, as those hold
+// the closest nodes of a random target and tighten the bound the most. the root of every class is
+// still checked against the bound, so a query costs a little for each class, which `classes`
+// measures
+//
+// it stands alone rather than as a backend of `Overlay` or `Classified`, as it cannot take nodes
+// in or out once built. `classes` compares it with `Classified`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UnifiedOverlay {
+    node_ids: Vec<classified::NodeId>,
+    forks: Vec<Fork>,
+    roots: Vec<Root>,
+}
+
+// the nodes of a class
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Root {
+    lo: u32,
+    hi: u32,
+    fork_index: u32,
+    class: Class,
+    // the key bits shared by every node of the class, and the mask of them, so that the bound of
+    // a class takes no lookup into `node_ids` or `forks`
+    prefix: NodeId,
+    prefix_mask: NodeId,
+}
+
+impl Root {
+    // the distance of the closest node the class may hold
+    fn bound(&self, target: Target) -> Distance {
+        ((self.prefix ^ (target << self.class)) & self.prefix_mask) >> self.class
+    }
+}
+
+fn key((id, class): classified::NodeId) -> NodeId {
+    id << class
+}
+
+impl UnifiedOverlay {
+    pub fn from_classified_node_ids(mut node_ids: Vec<classified::NodeId>) -> Self {
+        node_ids.sort_unstable_by_key(|&(id, class)| (class, key((id, class)), id));
+        let mut forks = Vec::with_capacity(node_ids.len());
+        let mut roots = Vec::new();
+        let mut lo = 0;
+        while lo < node_ids.len() {
+            let class = node_ids[lo].1;
+            let hi = lo + node_ids[lo..].partition_point(|&(_, c)| c == class);
+            let fork_index = forks.len();
+            build(&node_ids, lo, hi, &key, &mut forks);
+            // a single node is bounded by its exact distance
+            let prefix_mask = match forks.get(fork_index) {
+                Some(fork) if hi - lo > 1 && fork.level != BUCKET_LEVEL => {
+                    (!0u64).checked_shl(fork.level + 1).unwrap_or(0)
+                }
+                _ => !0,
+            };
+            roots.push(Root {
+                lo: lo as _,
+                hi: hi as _,
+                fork_index: fork_index as _,
+                class,
+                prefix: key(node_ids[lo]) & prefix_mask,
+                prefix_mask,
+            });
+            lo = hi
+        }
+        // the expected distance of the closest node of a class to a random target is inverse to
+        // the number of its nodes times the target space each of them covers
+        roots.sort_unstable_by_key(|root| {
+            std::cmp::Reverse(((root.hi - root.lo) as u128) << root.class)
+        });
+        Self {
+            node_ids,
+            forks,
+            roots,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.node_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node_ids.is_empty()
+    }
+
+    pub fn node_ids(&self) -> &[classified::NodeId] {
+        &self.node_ids
+    }
+
+    // the same results in the same order as `classified::find`
+    pub fn find(&self, target: Target, count: usize) -> Vec<NodeId> {
+        // the closest nodes so far in order, against which the ranges left to visit are bounded
+        let mut closest = Vec::<(Distance, NodeId)>::with_capacity(count + 1);
+        if count == 0 {
+            return Vec::new();
+        }
+        for root in &self.roots {
+            // a node at the bound may still win on id
+            if closest.len() == count && root.bound(target) > closest[count - 1].0 {
+                continue;
+            }
+            let (lo, hi, fork_index) = (root.lo as _, root.hi as _, root.fork_index as _);
+            self.visit(target, count, lo, hi, fork_index, &mut closest)
+        }
+        closest.into_iter().map(|(_, id)| id).collect()
+    }
+
+    // the distance of the closest node the range may hold
+    fn bound(&self, target: Target, lo: usize, hi: usize, fork_index: usize) -> Distance {
+        let (id, class) = self.node_ids[lo];
+        if hi - lo == 1 {
+            return classified::distance(id, target, class);
+        }
+        // every key in the range shares the bits above the fork level, and the bits below can be
+        // the ones of the target
+        let prefix_mask = match self.forks[fork_index].level {
+            BUCKET_LEVEL => !0,
+            level => (!0u64).checked_shl(level + 1).unwrap_or(0),
+        };
+        ((key((id, class)) ^ (target << class)) & prefix_mask) >> class
+    }
+
+    fn visit(
+        &self,
+        target: Target,
+        count: usize,
+        lo: usize,
+        hi: usize,
+        fork_index: usize,
+        closest: &mut Vec<(Distance, NodeId)>,
+    ) {
+        let (id, class) = self.node_ids[lo];
+        if hi - lo == 1 {
+            let node = (classified::distance(id, target, class), id);
+            if closest.len() == count && node >= closest[count - 1] {
+                return;
+            }
+            let index = closest.partition_point(|&other| other < node);
+            closest.insert(index, node);
+            closest.truncate(count);
+            return;
+        }
+        let fork = self.forks[fork_index];
+        // a node at the bound may still win on id
+        if closest.len() == count && self.bound(target, lo, hi, fork_index) > closest[count - 1].0 {
+            return;
+        }
+        let target_key = target << class;
+        if fork.level == BUCKET_LEVEL {
+            for index in lo..hi {
+                self.visit(target, count, index, index + 1, 0, closest)
+            }
+            return;
+        }
+        let mid = fork.mid as usize;
+        let zero = (lo, mid, fork_index + 1);
+        let one = (mid, hi, fork.one as usize);
+        let (near, far) = if (target_key >> fork.level) & 1 == 0 {
+            (zero, one)
+        } else {
+            (one, zero)
+        };
+        self.visit(target, count, near.0, near.1, near.2, closest);
+        self.visit(target, count, far.0, far.1, far.2, closest)
+    }
+}